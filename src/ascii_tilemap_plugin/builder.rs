@@ -1,15 +1,19 @@
-use super::Layer;
+use super::{layout::ResizePolicy, Layer};
 use bevy::prelude::*;
 
 #[derive(Debug, Clone)]
 pub struct TilemapBuilder {
     pub layers: Vec<LayerDataBuilder>,
+    pub resize_policy: ResizePolicy,
 }
 
 impl TilemapBuilder {
     #[must_use]
     pub fn new() -> Self {
-        Self { layers: vec![] }
+        Self {
+            layers: vec![],
+            resize_policy: ResizePolicy::default(),
+        }
     }
 
     #[must_use]
@@ -21,6 +25,12 @@ impl TilemapBuilder {
         self.layers.push(layer.clone());
         self
     }
+
+    /// What happens to the layers when the window is resized
+    pub fn resize_policy(&mut self, resize_policy: ResizePolicy) -> &mut Self {
+        self.resize_policy = resize_policy;
+        self
+    }
 }
 
 impl Default for TilemapBuilder {
@@ -30,6 +40,7 @@ impl Default for TilemapBuilder {
 }
 
 #[derive(Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct LayerDataBuilder {
     pub texture_path: Option<String>,
     pub size: Option<UVec2>,
@@ -39,6 +50,15 @@ pub struct LayerDataBuilder {
     pub id: u16,
     pub is_transparent: bool,
     pub is_background_transparent: bool,
+    /// Pixels between the bottom left corner of the first layer and the layer
+    pub offset: Vec2,
+    /// Size of the tiles relative to `tile_size`
+    pub scale: f32,
+    /// Layers with a higher z order are drawn over the others, the id when it's not set
+    pub z_order: Option<u16>,
+    pub is_visible: bool,
+    /// Keeps `size` whatever the size of the first layer
+    pub is_fixed_size: bool,
 }
 
 impl LayerDataBuilder {
@@ -51,6 +71,11 @@ impl LayerDataBuilder {
             id: id.into(),
             is_transparent: false,
             is_background_transparent: false,
+            offset: Vec2::ZERO,
+            scale: 1.,
+            z_order: None,
+            is_visible: true,
+            is_fixed_size: false,
         }
     }
 
@@ -59,6 +84,7 @@ impl LayerDataBuilder {
         self
     }
 
+    /// Size in tiles, only the first layer and the layers of fixed size need it
     pub fn size(&mut self, width: u32, height: u32) -> &mut Self {
         self.size = Some(UVec2::new(width, height));
         self
@@ -85,14 +111,53 @@ impl LayerDataBuilder {
         self
     }
 
-    pub(super) fn build_layer(&self) -> Layer {
+    /// Moves the layer by some pixels, only the tilemap backend places the layers in pixels
+    pub fn offset(&mut self, x: f32, y: f32) -> &mut Self {
+        self.offset = Vec2::new(x, y);
+        self
+    }
+
+    /// Scales the tiles of the layer, only the tilemap backend places the layers in pixels
+    pub fn scale(&mut self, scale: f32) -> &mut Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn z_order(&mut self, z_order: u16) -> &mut Self {
+        self.z_order = Some(z_order);
+        self
+    }
+
+    /// Whether the layer is shown at first, see `DrawContext::set_layer_visible`
+    pub fn is_visible(&mut self, is_visible: bool) -> &mut Self {
+        self.is_visible = is_visible;
+        self
+    }
+
+    /// Layers which aren't fixed fill the first layer from their offset, with as many
+    /// scaled tiles as fit in it
+    pub fn is_fixed_size(&mut self, is_fixed_size: bool) -> &mut Self {
+        self.is_fixed_size = is_fixed_size;
+        self
+    }
+
+    /// Size of the tiles on screen, in pixels
+    pub(super) fn scaled_tile_size(&self) -> Vec2 {
+        self.tile_size.expect("tile_size not set") * self.scale
+    }
+
+    /// The layer is built with the given size, it differs from `size` when the window
+    /// is resized with `ResizePolicy::Resize`
+    pub(super) fn build_layer(&self, size: UVec2) -> Layer {
         Layer {
             background_id: self.id * 2,
             foreground_id: self.id * 2 + 1,
             command_buffer: vec![],
-            size: self.size.expect("layer.size not set"),
+            size,
             is_background_transparent: self.is_background_transparent,
             is_transparent: self.is_transparent,
+            z_order: self.z_order.unwrap_or(self.id),
+            is_visible: self.is_visible,
         }
     }
 }
@@ -0,0 +1,84 @@
+/// Unicode characters of the code page 437 glyphs, in the order of the tilesheet
+#[rustfmt::skip]
+pub const CP437: [char; 256] = [
+    '\0', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?',
+    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '\\', ']', '^', '_',
+    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~', '⌂',
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Drawn for characters that aren't in the tilesheet
+pub const FALLBACK_GLYPH: u8 = b'?';
+
+/// Index in the tilesheet of a character, unmapped characters use `FALLBACK_GLYPH`
+#[must_use]
+#[rustfmt::skip]
+#[allow(clippy::cast_possible_truncation)]
+pub fn to_cp437(c: char) -> u8 {
+    // the characters of `CP437`, matched instead of searched for every glyph
+    match c {
+        '\0' | ' '..='~' => c as u8,
+        '☺' => 1, '☻' => 2, '♥' => 3, '♦' => 4, '♣' => 5, '♠' => 6, '•' => 7, '◘' => 8,
+        '○' => 9, '◙' => 10, '♂' => 11, '♀' => 12, '♪' => 13, '♫' => 14, '☼' => 15, '►' => 16,
+        '◄' => 17, '↕' => 18, '‼' => 19, '¶' => 20, '§' => 21, '▬' => 22, '↨' => 23, '↑' => 24,
+        '↓' => 25, '→' => 26, '←' => 27, '∟' => 28, '↔' => 29, '▲' => 30, '▼' => 31, '⌂' => 127,
+        'Ç' => 128, 'ü' => 129, 'é' => 130, 'â' => 131, 'ä' => 132, 'à' => 133, 'å' => 134, 'ç' => 135,
+        'ê' => 136, 'ë' => 137, 'è' => 138, 'ï' => 139, 'î' => 140, 'ì' => 141, 'Ä' => 142, 'Å' => 143,
+        'É' => 144, 'æ' => 145, 'Æ' => 146, 'ô' => 147, 'ö' => 148, 'ò' => 149, 'û' => 150, 'ù' => 151,
+        'ÿ' => 152, 'Ö' => 153, 'Ü' => 154, '¢' => 155, '£' => 156, '¥' => 157, '₧' => 158, 'ƒ' => 159,
+        'á' => 160, 'í' => 161, 'ó' => 162, 'ú' => 163, 'ñ' => 164, 'Ñ' => 165, 'ª' => 166, 'º' => 167,
+        '¿' => 168, '⌐' => 169, '¬' => 170, '½' => 171, '¼' => 172, '¡' => 173, '«' => 174, '»' => 175,
+        '░' => 176, '▒' => 177, '▓' => 178, '│' => 179, '┤' => 180, '╡' => 181, '╢' => 182, '╖' => 183,
+        '╕' => 184, '╣' => 185, '║' => 186, '╗' => 187, '╝' => 188, '╜' => 189, '╛' => 190, '┐' => 191,
+        '└' => 192, '┴' => 193, '┬' => 194, '├' => 195, '─' => 196, '┼' => 197, '╞' => 198, '╟' => 199,
+        '╚' => 200, '╔' => 201, '╩' => 202, '╦' => 203, '╠' => 204, '═' => 205, '╬' => 206, '╧' => 207,
+        '╨' => 208, '╤' => 209, '╥' => 210, '╙' => 211, '╘' => 212, '╒' => 213, '╓' => 214, '╫' => 215,
+        '╪' => 216, '┘' => 217, '┌' => 218, '█' => 219, '▄' => 220, '▌' => 221, '▐' => 222, '▀' => 223,
+        'α' => 224, 'ß' => 225, 'Γ' => 226, 'π' => 227, 'Σ' => 228, 'σ' => 229, 'µ' => 230, 'τ' => 231,
+        'Φ' => 232, 'Θ' => 233, 'Ω' => 234, 'δ' => 235, '∞' => 236, 'φ' => 237, 'ε' => 238, '∩' => 239,
+        '≡' => 240, '±' => 241, '≥' => 242, '≤' => 243, '⌠' => 244, '⌡' => 245, '÷' => 246, '≈' => 247,
+        '°' => 248, '∙' => 249, '·' => 250, '√' => 251, 'ⁿ' => 252, '²' => 253, '■' => 254, '\u{a0}' => 255,
+        _ => FALLBACK_GLYPH,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_mapping() {
+        for (index, c) in CP437.iter().enumerate() {
+            assert_eq!(usize::from(to_cp437(*c)), index, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn common_glyphs() {
+        assert_eq!(to_cp437('@'), 64);
+        assert_eq!(to_cp437('█'), 219);
+        assert_eq!(to_cp437('░'), 176);
+        assert_eq!(to_cp437('♥'), 3);
+        assert_eq!(to_cp437('┌'), 218);
+        assert_eq!(to_cp437('═'), 205);
+    }
+
+    #[test]
+    fn unmapped_glyphs() {
+        assert_eq!(to_cp437('€'), FALLBACK_GLYPH);
+        assert_eq!(to_cp437('\n'), FALLBACK_GLYPH);
+        assert_eq!(to_cp437('Û'), FALLBACK_GLYPH);
+    }
+}
@@ -1,11 +1,47 @@
 use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 
-use super::{DrawCommand, Layer, LayerEntities};
+use super::{
+    cp437::to_cp437,
+    geometry::{line, Rect},
+    rich_text::{parse, plain, wrap, RichChar, TextAlign},
+    DrawCommand, Layer, LayerEntities,
+};
 
 #[derive(Debug, Clone)]
 pub struct ActiveLayer(pub u16);
 
+/// Glyphs used to draw the frame of a box
+struct BoxGlyphs {
+    top_left: char,
+    top_right: char,
+    bottom_left: char,
+    bottom_right: char,
+    horizontal: char,
+    vertical: char,
+}
+
+const SINGLE_LINE: BoxGlyphs = BoxGlyphs {
+    top_left: '┌',
+    top_right: '┐',
+    bottom_left: '└',
+    bottom_right: '┘',
+    horizontal: '─',
+    vertical: '│',
+};
+
+const DOUBLE_LINE: BoxGlyphs = BoxGlyphs {
+    top_left: '╔',
+    top_right: '╗',
+    bottom_left: '╚',
+    bottom_right: '╝',
+    horizontal: '═',
+    vertical: '║',
+};
+
+const FULL_BLOCK: char = '█';
+const LIGHT_SHADE: char = '░';
+
 #[derive(SystemParam)]
 pub struct DrawContext<'w, 's> {
     layers: Query<'w, 's, &'static mut Layer>,
@@ -14,6 +50,7 @@ pub struct DrawContext<'w, 's> {
 }
 
 impl<'world, 'state> DrawContext<'world, 'state> {
+    /// Sets a tile of the active layer, the glyph is drawn with its code page 437 tile
     pub fn set(&mut self, x: u32, y: u32, background: Color, foreground: Color, glyph: char) {
         let entity = self.layer_entities[self.active_layer.0 as usize];
         if let Ok(mut layer) = self.layers.get_mut(entity) {
@@ -27,13 +64,13 @@ impl<'world, 'state> DrawContext<'world, 'state> {
                 y,
                 background,
                 foreground,
-                glyph,
+                glyph: to_cp437(glyph),
             });
         }
     }
 
     /// Prints a string at the given position with foreground and background color
-    /// if the string is longer than the viewport it will get truncated, wrapping is not handled
+    /// if the string is longer than the viewport it will get truncated, use `print_color_wrapped` to wrap it
     #[allow(clippy::cast_possible_truncation)]
     pub fn print_color(
         &mut self,
@@ -59,7 +96,7 @@ impl<'world, 'state> DrawContext<'world, 'state> {
     ) {
         let size = self.get_active_layer_size();
         self.print_color(
-            (size.x / 2) - (text.to_string().len() as u32 / 2),
+            (size.x / 2).saturating_sub(text.chars().count() as u32 / 2),
             y,
             background,
             foreground,
@@ -68,7 +105,7 @@ impl<'world, 'state> DrawContext<'world, 'state> {
     }
 
     /// Prints a string at the given position
-    /// if the string is longer than the viewport it will get truncated, wrapping is not handled
+    /// if the string is longer than the viewport it will get truncated, use `print_wrapped` to wrap it
     pub fn print(&mut self, x: u32, y: u32, text: &str) {
         self.print_color(x, y, Color::BLACK, Color::WHITE, text);
     }
@@ -78,6 +115,91 @@ impl<'world, 'state> DrawContext<'world, 'state> {
         self.print_color_centered(y, Color::BLACK, Color::WHITE, text);
     }
 
+    /// Prints a string wrapped on word boundaries into the Rect.
+    /// Lines that don't fit are dropped, returns the number of lines printed
+    pub fn print_wrapped(&mut self, rect: Rect, align: TextAlign, text: &str) -> u32 {
+        self.print_color_wrapped(rect, align, Color::BLACK, Color::WHITE, text)
+    }
+
+    /// Prints a string wrapped on word boundaries into the Rect with foreground and background color.
+    /// Lines that don't fit are dropped, returns the number of lines printed
+    pub fn print_color_wrapped(
+        &mut self,
+        rect: Rect,
+        align: TextAlign,
+        background: Color,
+        foreground: Color,
+        text: &str,
+    ) -> u32 {
+        self.print_lines(rect, align, background, foreground, &plain(text))
+    }
+
+    /// Prints a text with color markup like `#[red]Orc#[] hits you`, wrapped into the Rect.
+    /// Lines that don't fit are dropped, returns the number of lines printed
+    pub fn print_rich(
+        &mut self,
+        rect: Rect,
+        background: Color,
+        foreground: Color,
+        text: &str,
+    ) -> u32 {
+        self.print_lines(rect, TextAlign::Left, background, foreground, &parse(text))
+    }
+
+    /// Prints glyphs returned by `rich_text::parse` or `rich_text::plain`, wrapped into the Rect.
+    /// Lines that don't fit are dropped, returns the number of lines printed
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn print_lines(
+        &mut self,
+        rect: Rect,
+        align: TextAlign,
+        background: Color,
+        foreground: Color,
+        text: &[RichChar],
+    ) -> u32 {
+        let origin = rect.min();
+        let width = rect.width() as usize;
+        let lines = wrap(text, width);
+        let count = usize::min(lines.len(), rect.height() as usize);
+        for (i, line) in lines.iter().take(count).enumerate() {
+            let x = origin.x + align.offset(width, line.len()) as u32;
+            self.print_rich_line(x, origin.y + i as u32, background, foreground, line);
+        }
+        count as u32
+    }
+
+    /// Prints a text with color markup centered on the x axis, wrapping is not handled
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn print_rich_centered(
+        &mut self,
+        y: u32,
+        background: Color,
+        foreground: Color,
+        text: &str,
+    ) {
+        let line = parse(text);
+        let size = self.get_active_layer_size();
+        let x = (size.x / 2).saturating_sub(line.len() as u32 / 2);
+        self.print_rich_line(x, y, background, foreground, &line);
+    }
+
+    /// Prints glyphs returned by `rich_text::parse` or `rich_text::wrap`,
+    /// the foreground is used for glyphs without a color
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn print_rich_line(
+        &mut self,
+        x: u32,
+        y: u32,
+        background: Color,
+        foreground: Color,
+        line: &[RichChar],
+    ) {
+        for (i, c) in line.iter().enumerate() {
+            let color = c.foreground.unwrap_or(foreground);
+            self.set(x + i as u32, y, background, color, c.glyph);
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     #[allow(clippy::cast_sign_loss)]
     #[allow(clippy::cast_precision_loss)]
@@ -96,13 +218,94 @@ impl<'world, 'state> DrawContext<'world, 'state> {
         let fill_width = (percent * width as f32) as u32;
         for x in 0..width {
             if x <= fill_width {
-                self.set(start_x + x, start_y, background, foreground, 219 as char);
+                self.set(start_x + x, start_y, background, foreground, FULL_BLOCK);
             } else {
-                self.set(start_x + x, start_y, background, foreground, '░');
+                self.set(start_x + x, start_y, background, foreground, LIGHT_SHADE);
             }
         }
     }
 
+    /// Draws a vertical bar filled from the bottom
+    #[allow(clippy::too_many_arguments)]
+    #[allow(clippy::cast_sign_loss)]
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn bar_vertical(
+        &mut self,
+        start_x: u32,
+        start_y: u32,
+        height: u32,
+        filled_amount: u32,
+        max: u32,
+        background: Color,
+        foreground: Color,
+    ) {
+        let percent = filled_amount as f32 / max as f32;
+        let fill_height = (percent * height as f32) as u32;
+        for y in 0..height {
+            let glyph = if height - y <= fill_height {
+                FULL_BLOCK
+            } else {
+                LIGHT_SHADE
+            };
+            self.set(start_x, start_y + y, background, foreground, glyph);
+        }
+    }
+
+    /// Draws the frame of the Rect with single lines, the inside is left untouched
+    pub fn draw_box(&mut self, rect: Rect, background: Color, foreground: Color) {
+        self.draw_frame(rect, background, foreground, &SINGLE_LINE);
+    }
+
+    /// Draws the frame of the Rect with double lines, the inside is left untouched
+    pub fn draw_box_double(&mut self, rect: Rect, background: Color, foreground: Color) {
+        self.draw_frame(rect, background, foreground, &DOUBLE_LINE);
+    }
+
+    fn draw_frame(&mut self, rect: Rect, background: Color, foreground: Color, glyphs: &BoxGlyphs) {
+        if rect.width() < 2 || rect.height() < 2 {
+            return;
+        }
+        let min = rect.min();
+        let max = min + UVec2::new(rect.width() - 1, rect.height() - 1);
+        // only the visible part of the frame is drawn
+        let size = self.get_active_layer_size();
+        for x in min.x + 1..max.x.min(size.x) {
+            self.set(x, min.y, background, foreground, glyphs.horizontal);
+            self.set(x, max.y, background, foreground, glyphs.horizontal);
+        }
+        for y in min.y + 1..max.y.min(size.y) {
+            self.set(min.x, y, background, foreground, glyphs.vertical);
+            self.set(max.x, y, background, foreground, glyphs.vertical);
+        }
+        self.set(min.x, min.y, background, foreground, glyphs.top_left);
+        self.set(max.x, min.y, background, foreground, glyphs.top_right);
+        self.set(min.x, max.y, background, foreground, glyphs.bottom_left);
+        self.set(max.x, max.y, background, foreground, glyphs.bottom_right);
+    }
+
+    /// Sets every tile of the Rect to the same glyph
+    pub fn fill_rect(&mut self, rect: Rect, background: Color, foreground: Color, glyph: char) {
+        let size = self.get_active_layer_size();
+        for point in rect.clamp_to(size).points() {
+            self.set(point.x, point.y, background, foreground, glyph);
+        }
+    }
+
+    /// Draws a line between two points, both ends included
+    pub fn draw_line(
+        &mut self,
+        start: UVec2,
+        end: UVec2,
+        background: Color,
+        foreground: Color,
+        glyph: char,
+    ) {
+        for point in line(start, end) {
+            self.set(point.x, point.y, background, foreground, glyph);
+        }
+    }
+
     /// Clears the active layer
     pub fn cls(&mut self) {
         self.cls_color(Color::BLACK);
@@ -130,9 +333,173 @@ impl<'world, 'state> DrawContext<'world, 'state> {
         self.active_layer.0 = u16::from(layer);
     }
 
+    /// Shows or hides a layer, a hidden layer keeps what is drawn to it
+    pub fn set_layer_visible(&mut self, layer: u8, is_visible: bool) {
+        let entity = self.layer_entities[usize::from(layer)];
+        if let Ok(mut layer) = self.layers.get_mut(entity) {
+            layer.is_visible = is_visible;
+        }
+    }
+
     pub fn get_active_layer_size(&mut self) -> UVec2 {
         let entity = self.layer_entities[self.active_layer.0 as usize];
         let layer = self.layers.get_mut(entity).expect("layer not found");
         layer.size
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ascii_tilemap_plugin::LayerDataBuilder;
+    use bevy::{ecs::schedule::IntoSystemDescriptor, utils::HashMap};
+
+    const WIDTH: u32 = 6;
+    const HEIGHT: u32 = 4;
+
+    /// Runs a system drawing on a 6x4 layer, returns the glyph of every tile drawn
+    fn drawn_tiles<Params>(system: impl IntoSystemDescriptor<Params>) -> HashMap<UVec2, u8> {
+        let mut world = World::new();
+        let layer = world
+            .spawn()
+            .insert(LayerDataBuilder::new(0u16).build_layer(UVec2::new(WIDTH, HEIGHT)))
+            .id();
+        world.insert_resource::<LayerEntities>(vec![layer]);
+        world.insert_resource(ActiveLayer(0));
+        SystemStage::parallel().with_system(system).run(&mut world);
+        let layer = world.get::<Layer>(layer).expect("no layer");
+        layer
+            .command_buffer
+            .iter()
+            .filter_map(|command| match *command {
+                DrawCommand::DrawTile { x, y, glyph, .. } => Some((UVec2::new(x, y), glyph)),
+                DrawCommand::ClearLayer { .. } => None,
+            })
+            .collect()
+    }
+
+    fn is_inside(tiles: &HashMap<UVec2, u8>) -> bool {
+        tiles.keys().all(|tile| tile.x < WIDTH && tile.y < HEIGHT)
+    }
+
+    /// The tiles of lines of text, each starting at a tile
+    fn text_tiles(lines: &[((u32, u32), &str)]) -> HashMap<UVec2, u8> {
+        lines
+            .iter()
+            .flat_map(|((x, y), text)| {
+                (*x..)
+                    .zip(text.chars())
+                    .map(move |(x, c)| (UVec2::new(x, *y), to_cp437(c)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn print_wrapped_aligns_the_lines_in_the_rect() {
+        fn left(mut ctx: DrawContext) {
+            let rect = Rect::with_dimension(1, 1, 4, 3);
+            assert_eq!(ctx.print_wrapped(rect, TextAlign::Left, "ab cd e"), 2);
+        }
+        fn center(mut ctx: DrawContext) {
+            let rect = Rect::with_dimension(1, 1, 4, 3);
+            assert_eq!(ctx.print_wrapped(rect, TextAlign::Center, "ab cd e"), 2);
+        }
+        fn right(mut ctx: DrawContext) {
+            let rect = Rect::with_dimension(1, 1, 4, 3);
+            assert_eq!(ctx.print_wrapped(rect, TextAlign::Right, "ab cd e"), 2);
+        }
+        assert_eq!(
+            drawn_tiles(left),
+            text_tiles(&[((1, 1), "ab"), ((1, 2), "cd e")])
+        );
+        assert_eq!(
+            drawn_tiles(center),
+            text_tiles(&[((2, 1), "ab"), ((1, 2), "cd e")])
+        );
+        assert_eq!(
+            drawn_tiles(right),
+            text_tiles(&[((3, 1), "ab"), ((1, 2), "cd e")])
+        );
+    }
+
+    #[test]
+    fn print_wrapped_drops_the_lines_that_dont_fit() {
+        fn system(mut ctx: DrawContext) {
+            let rect = Rect::with_dimension(4, 2, 2, 2);
+            assert_eq!(ctx.print_wrapped(rect, TextAlign::Left, "ab cd ef"), 2);
+        }
+        let tiles = drawn_tiles(system);
+        assert!(is_inside(&tiles));
+        assert_eq!(tiles, text_tiles(&[((4, 2), "ab"), ((4, 3), "cd")]));
+    }
+
+    #[test]
+    fn draw_box_is_clipped() {
+        fn system(mut ctx: DrawContext) {
+            ctx.draw_box(Rect::with_dimension(3, 1, 5, 5), Color::BLACK, Color::WHITE);
+        }
+        let tiles = drawn_tiles(system);
+        assert!(is_inside(&tiles));
+        let expected = [
+            ((3, 1), '┌'),
+            ((4, 1), '─'),
+            ((5, 1), '─'),
+            ((3, 2), '│'),
+            ((3, 3), '│'),
+        ]
+        .iter()
+        .map(|((x, y), glyph)| (UVec2::new(*x, *y), to_cp437(*glyph)))
+        .collect::<HashMap<_, _>>();
+        assert_eq!(tiles, expected);
+    }
+
+    #[test]
+    fn fill_rect_is_clipped() {
+        fn system(mut ctx: DrawContext) {
+            ctx.fill_rect(
+                Rect::with_dimension(4, 2, 10, 10),
+                Color::BLACK,
+                Color::WHITE,
+                '#',
+            );
+        }
+        let tiles = drawn_tiles(system);
+        assert!(is_inside(&tiles));
+        assert_eq!(tiles.len(), 4);
+        assert!(tiles.values().all(|glyph| *glyph == to_cp437('#')));
+    }
+
+    #[test]
+    fn draw_line_is_clipped() {
+        fn system(mut ctx: DrawContext) {
+            ctx.draw_line(
+                UVec2::ZERO,
+                UVec2::new(9, 3),
+                Color::BLACK,
+                Color::WHITE,
+                '*',
+            );
+        }
+        let tiles = drawn_tiles(system);
+        assert!(is_inside(&tiles));
+        let visible = line(UVec2::ZERO, UVec2::new(9, 3))
+            .into_iter()
+            .filter(|point| point.x < WIDTH)
+            .count();
+        assert_eq!(tiles.len(), visible);
+        assert!(tiles.contains_key(&UVec2::ZERO));
+    }
+
+    #[test]
+    fn bar_vertical_is_clipped() {
+        fn system(mut ctx: DrawContext) {
+            ctx.bar_vertical(5, 2, 4, 4, 4, Color::BLACK, Color::WHITE);
+            ctx.bar_vertical(6, 0, 4, 4, 4, Color::BLACK, Color::WHITE);
+        }
+        let tiles = drawn_tiles(system);
+        assert!(is_inside(&tiles));
+        assert_eq!(tiles.len(), 2);
+        assert_eq!(tiles.get(&UVec2::new(5, 2)), Some(&to_cp437('█')));
+        assert_eq!(tiles.get(&UVec2::new(5, 3)), Some(&to_cp437('█')));
+    }
+}
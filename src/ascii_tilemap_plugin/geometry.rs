@@ -38,6 +38,21 @@ impl Rect {
         )
     }
 
+    /// Returns the corner with the smallest coordinates
+    #[must_use]
+    pub fn min(&self) -> UVec2 {
+        UVec2::new(self.x_left, self.y_bottom)
+    }
+
+    /// Returns the part of the Rect inside an area of the given size starting at 0,0,
+    /// it's empty when the Rect is outside
+    #[must_use]
+    pub fn clamp_to(&self, size: UVec2) -> Rect {
+        let min = self.min().min(size);
+        let max = UVec2::new(self.x_right, self.y_top).min(size);
+        Self::with_dimension(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
     /// Returns true if a point is inside the Rect
     #[allow(unused)]
     #[must_use]
@@ -77,6 +92,31 @@ impl Rect {
     }
 }
 
+/// Returns the points of a line between two points with Bresenham's algorithm,
+/// both ends are included
+#[must_use]
+pub fn line(start: UVec2, end: UVec2) -> Vec<UVec2> {
+    let (start, end) = (start.as_ivec2(), end.as_ivec2());
+    let delta = (end - start).abs();
+    let step = (end - start).signum();
+    let mut error = delta.x - delta.y;
+    let mut current = start;
+    let mut points = vec![current.as_uvec2()];
+    while current != end {
+        let double_error = error * 2;
+        if double_error > -delta.y {
+            error -= delta.y;
+            current.x += step.x;
+        }
+        if double_error < delta.x {
+            error += delta.x;
+            current.y += step.y;
+        }
+        points.push(current.as_uvec2());
+    }
+    points
+}
+
 pub struct Points {
     curr_index: u32,
     width: u32,
@@ -118,6 +158,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn clamp_to() {
+        let size = UVec2::new(10, 5);
+        let rect = super::Rect::with_dimension(8, 2, 4, 4).clamp_to(size);
+        assert_eq!(rect.min(), UVec2::new(8, 2));
+        assert_eq!((rect.width(), rect.height()), (2, 3));
+        let outside = super::Rect::with_dimension(12, 1, 3, 3).clamp_to(size);
+        assert_eq!(outside.points().count(), 0);
+    }
+
+    #[test]
+    fn bresenham_line() {
+        assert_eq!(
+            line(UVec2::new(0, 0), UVec2::new(4, 2)),
+            vec![
+                UVec2::new(0, 0),
+                UVec2::new(1, 0),
+                UVec2::new(2, 1),
+                UVec2::new(3, 1),
+                UVec2::new(4, 2),
+            ]
+        );
+        assert_eq!(
+            line(UVec2::new(2, 3), UVec2::new(2, 1)),
+            vec![UVec2::new(2, 3), UVec2::new(2, 2), UVec2::new(2, 1)]
+        );
+        assert_eq!(line(UVec2::ONE, UVec2::ONE), vec![UVec2::ONE]);
+    }
+
     #[test]
     fn iter_offset() {
         let rect = super::Rect::with_dimension(1, 1, 2, 2);
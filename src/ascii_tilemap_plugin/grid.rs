@@ -0,0 +1,252 @@
+use std::fmt;
+
+use bevy::prelude::*;
+
+use super::{
+    cp437::CP437,
+    render::{RenderLayers, TileRenderData},
+    Layer,
+};
+
+/// A composited tile, what a terminal character shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub glyph: char,
+    pub foreground: Color,
+    pub background: Color,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            glyph: ' ',
+            foreground: Color::WHITE,
+            background: Color::BLACK,
+        }
+    }
+}
+
+/// The layers composited into cells, row 0 is the top of the screen
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Grid {
+    pub size: UVec2,
+    pub cells: Vec<Cell>,
+}
+
+impl Grid {
+    #[must_use]
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            cells: vec![Cell::default(); (size.x * size.y) as usize],
+        }
+    }
+
+    /// The glyphs of a row, empty when it's out of the grid
+    #[must_use]
+    pub fn row(&self, y: u32) -> String {
+        if y >= self.size.y {
+            return String::new();
+        }
+        let start = (y * self.size.x) as usize;
+        self.cells[start..start + self.size.x as usize]
+            .iter()
+            .map(|cell| cell.glyph)
+            .collect()
+    }
+}
+
+/// The glyphs of every row, the colors are left out
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.size.y {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", self.row(y))?;
+        }
+        Ok(())
+    }
+}
+
+/// Composites the visible layers by z order with one cell per tile of the layer with
+/// the most tiles, the tiles of smaller layers cover several cells.
+/// A drawn background hides the layers below, empty glyphs let them show through.
+/// The layers are stretched over the whole grid, their pixel offset and scale are left out.
+#[must_use]
+pub fn compose(layers: &[&Layer], render_layers: &RenderLayers) -> Grid {
+    let size = layers
+        .iter()
+        .map(|layer| layer.size)
+        .fold(UVec2::ZERO, UVec2::max);
+    let mut grid = Grid::new(size);
+    let mut layers = layers
+        .iter()
+        .filter(|layer| layer.is_visible)
+        .collect::<Vec<_>>();
+    layers.sort_by_key(|layer| layer.z_order);
+    for layer in layers {
+        let background = &render_layers[layer.background_id as usize];
+        let foreground = &render_layers[layer.foreground_id as usize];
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let tile = UVec2::new(x * layer.size.x / size.x, y * layer.size.y / size.y);
+                // the render layers start at the bottom row
+                let index = ((layer.size.y - 1 - tile.y) * layer.size.x + tile.x) as usize;
+                let cell = &mut grid.cells[(y * size.x + x) as usize];
+                // transparent backgrounds have no tiles
+                if let Some(tile) = background.get(index).filter(|tile| tile.glyph != 0) {
+                    cell.glyph = ' ';
+                    cell.background = tile.color;
+                }
+                if let Some(tile) = foreground.get(index).filter(|tile| is_visible(tile)) {
+                    cell.glyph = CP437.get(usize::from(tile.glyph)).copied().unwrap_or('?');
+                    cell.foreground = tile.color;
+                }
+            }
+        }
+    }
+    grid
+}
+
+/// Empty glyphs are transparent in the tilesheet
+fn is_visible(tile: &TileRenderData) -> bool {
+    tile.glyph != 0 && tile.glyph != u16::from(b' ')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        apply_commands, cp437::to_cp437, new_render_layers, render::TileBuffer, DrawCommand,
+        LayerDataBuilder,
+    };
+    use super::*;
+
+    /// A 2 by 2 opaque map under a 4 by 4 hud with a background and a transparent overlay
+    fn layers() -> (Vec<Layer>, RenderLayers) {
+        let builders = [
+            LayerDataBuilder::new(0_u16),
+            LayerDataBuilder::new(1_u16)
+                .is_transparent(true)
+                .is_background_transparent(false)
+                .clone(),
+            LayerDataBuilder::new(2_u16)
+                .is_transparent(true)
+                .is_background_transparent(true)
+                .clone(),
+        ];
+        let sizes = [UVec2::new(2, 2), UVec2::new(4, 4), UVec2::new(4, 4)];
+        let layers = builders
+            .iter()
+            .zip(sizes)
+            .map(|(builder, size)| builder.build_layer(size))
+            .collect::<Vec<_>>();
+        let render_layers = new_render_layers(&layers);
+        (layers, render_layers)
+    }
+
+    fn draw_tile(layer: &mut Layer, x: u32, y: u32, background: Color, glyph: char) {
+        layer.command_buffer.push(DrawCommand::DrawTile {
+            x,
+            y,
+            background,
+            foreground: Color::YELLOW,
+            glyph: to_cp437(glyph),
+        });
+    }
+
+    fn clear(layer: &mut Layer, color: Color) {
+        layer.command_buffer.push(DrawCommand::ClearLayer { color });
+    }
+
+    fn render(layers: &mut [Layer], render_layers: &mut RenderLayers) -> Grid {
+        for layer in layers.iter_mut() {
+            apply_commands(layer, render_layers, &mut TileBuffer::default());
+        }
+        compose(&layers.iter().collect::<Vec<_>>(), render_layers)
+    }
+
+    fn cell(grid: &Grid, x: u32, y: u32) -> Cell {
+        grid.cells[(y * grid.size.x + x) as usize]
+    }
+
+    #[test]
+    fn layers_are_composited() {
+        let (mut layers, mut render_layers) = layers();
+        for layer in &mut layers {
+            clear(layer, Color::BLACK);
+        }
+        draw_tile(&mut layers[0], 0, 0, Color::DARK_GRAY, '#');
+        draw_tile(&mut layers[0], 1, 1, Color::BLACK, '.');
+        draw_tile(&mut layers[1], 3, 0, Color::BLUE, '♥');
+        draw_tile(&mut layers[2], 3, 3, Color::BLACK, '@');
+        let grid = render(&mut layers, &mut render_layers);
+
+        assert_eq!(grid.size, UVec2::new(4, 4));
+        // the map tiles cover 2 by 2 cells
+        assert_eq!(grid.to_string(), "## ♥\n##  \n  ..\n  .@");
+        assert_eq!(cell(&grid, 1, 1).background, Color::DARK_GRAY);
+        assert_eq!(cell(&grid, 3, 0).background, Color::BLUE);
+        // the transparent background keeps the map background
+        let player = cell(&grid, 3, 3);
+        assert_eq!(player.foreground, Color::YELLOW);
+        assert_eq!(player.background, Color::BLACK);
+    }
+
+    #[test]
+    fn backgrounds_hide_the_layers_below() {
+        let (mut layers, mut render_layers) = layers();
+        clear(&mut layers[0], Color::BLACK);
+        draw_tile(&mut layers[0], 0, 0, Color::BLACK, '#');
+        // a cleared transparent layer shows everything below
+        clear(&mut layers[1], Color::BLACK);
+        clear(&mut layers[2], Color::BLACK);
+        let grid = render(&mut layers, &mut render_layers);
+        assert_eq!(grid.row(0), "##  ");
+
+        // a space with a background hides the glyph
+        draw_tile(&mut layers[1], 0, 0, Color::RED, ' ');
+        // a space without a background doesn't
+        draw_tile(&mut layers[2], 1, 0, Color::RED, ' ');
+        let grid = render(&mut layers, &mut render_layers);
+        assert_eq!(grid.row(0), " #  ");
+        assert_eq!(cell(&grid, 0, 0).background, Color::RED);
+    }
+
+    #[test]
+    fn z_order_and_visibility() {
+        let (mut layers, mut render_layers) = layers();
+        for layer in &mut layers {
+            clear(layer, Color::BLACK);
+        }
+        draw_tile(&mut layers[0], 0, 0, Color::BLACK, '#');
+        draw_tile(&mut layers[1], 0, 0, Color::BLUE, ' ');
+        draw_tile(&mut layers[2], 1, 0, Color::BLACK, '@');
+        // the map is drawn over the hud
+        layers[0].z_order = 5;
+        let grid = render(&mut layers, &mut render_layers);
+        assert_eq!(grid.row(0), "##  ");
+
+        layers[0].is_visible = false;
+        layers[2].is_visible = false;
+        let grid = render(&mut layers, &mut render_layers);
+        assert_eq!(grid.row(0), "    ");
+        assert_eq!(cell(&grid, 0, 0).background, Color::BLUE);
+        assert_eq!(cell(&grid, 2, 0).background, Color::BLACK);
+    }
+
+    #[test]
+    fn single_layer() {
+        let (mut layers, mut render_layers) = layers();
+        draw_tile(&mut layers[0], 1, 0, Color::BLACK, '#');
+        draw_tile(&mut layers[2], 0, 1, Color::BLACK, '@');
+        for layer in &mut layers {
+            apply_commands(layer, &mut render_layers, &mut TileBuffer::default());
+        }
+        let map = compose(&[&layers[0]], &render_layers);
+        assert_eq!(map.to_string(), " #\n  ");
+        let overlay = compose(&[&layers[2]], &render_layers);
+        assert_eq!(overlay.row(1), "@   ");
+        assert_eq!(overlay.row(4), "");
+    }
+}
@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+
+/// What the tilemap does when the window size doesn't match the size of its layers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResizePolicy {
+    /// Keeps the size of the layers and scales them to fit the window,
+    /// the space left on the sides is left empty
+    #[default]
+    Letterbox,
+    /// Keeps the size of the tiles and changes the number of tiles of the layers
+    Resize,
+}
+
+/// Where a layer is drawn, in tilemap pixels from the bottom left corner of the first layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerPlacement {
+    pub offset: Vec2,
+    /// Size of the tiles once scaled
+    pub tile_size: Vec2,
+    /// Size in tiles
+    pub size: UVec2,
+}
+
+/// Where the tilemap is drawn in the window, in pixels with y growing upward like the cursor
+#[derive(Debug, Clone, PartialEq)]
+pub struct TilemapLayout {
+    pub window_size: Vec2,
+    /// Size of the first layer, in tiles
+    pub size: UVec2,
+    pub tile_size: Vec2,
+    /// Window pixels per tilemap pixel
+    pub scale: f32,
+    /// Empty space between the bottom left corner of the window and the tilemap
+    pub offset: Vec2,
+    /// Where the layers are drawn, in the order of the builder
+    pub layers: Vec<LayerPlacement>,
+}
+
+impl TilemapLayout {
+    /// Places a layer of `size` tiles of `tile_size` pixels in the window
+    #[must_use]
+    pub fn new(policy: ResizePolicy, window_size: Vec2, size: UVec2, tile_size: Vec2) -> Self {
+        let (size, scale) = match policy {
+            ResizePolicy::Letterbox => {
+                let tilemap_size = size.as_vec2() * tile_size;
+                let scale = window_size / tilemap_size;
+                (size, scale.min_element())
+            }
+            ResizePolicy::Resize => (layer_size(window_size, tile_size), 1.),
+        };
+        let offset = (window_size - size.as_vec2() * tile_size * scale) / 2.;
+        Self {
+            window_size,
+            size,
+            tile_size,
+            scale,
+            offset: offset.max(Vec2::ZERO),
+            layers: vec![LayerPlacement {
+                offset: Vec2::ZERO,
+                tile_size,
+                size,
+            }],
+        }
+    }
+
+    /// The tile of a layer under a cursor position, y grows downward like in `DrawContext`
+    #[must_use]
+    pub fn tile_at<I: Into<u16>>(&self, cursor_position: Vec2, layer: I) -> Option<UVec2> {
+        let layer = self.layers.get(usize::from(layer.into()))?;
+        let position =
+            ((cursor_position - self.offset) / self.scale - layer.offset) / layer.tile_size;
+        if position.min_element() < 0. {
+            return None;
+        }
+        let tile = position.floor().as_uvec2();
+        if tile.x >= layer.size.x || tile.y >= layer.size.y {
+            return None;
+        }
+        Some(UVec2::new(tile.x, layer.size.y - 1 - tile.y))
+    }
+}
+
+/// Number of tiles of `tile_size` pixels fitting in the window, at least one
+#[must_use]
+pub fn layer_size(window_size: Vec2, tile_size: Vec2) -> UVec2 {
+    (window_size / tile_size).floor().as_uvec2().max(UVec2::ONE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The dungeon map, 40 by 25 tiles of 32 pixels
+    fn layout(policy: ResizePolicy, window_width: f32, window_height: f32) -> TilemapLayout {
+        TilemapLayout::new(
+            policy,
+            Vec2::new(window_width, window_height),
+            UVec2::new(40, 25),
+            Vec2::new(32., 32.),
+        )
+    }
+
+    #[test]
+    fn letterbox_exact_fit() {
+        let layout = layout(ResizePolicy::Letterbox, 1280., 800.);
+        assert_eq!(layout.size, UVec2::new(40, 25));
+        assert!((layout.scale - 1.).abs() < f32::EPSILON);
+        assert_eq!(layout.offset, Vec2::ZERO);
+    }
+
+    #[test]
+    fn letterbox_wide_window() {
+        let layout = layout(ResizePolicy::Letterbox, 2000., 400.);
+        assert_eq!(layout.size, UVec2::new(40, 25));
+        assert!((layout.scale - 0.5).abs() < f32::EPSILON);
+        assert_eq!(layout.offset, Vec2::new(680., 0.));
+    }
+
+    #[test]
+    fn letterbox_tall_window() {
+        let layout = layout(ResizePolicy::Letterbox, 640., 1000.);
+        assert!((layout.scale - 0.5).abs() < f32::EPSILON);
+        assert_eq!(layout.offset, Vec2::new(0., 300.));
+    }
+
+    #[test]
+    fn resize() {
+        let layout = layout(ResizePolicy::Resize, 1000., 650.);
+        assert_eq!(layout.size, UVec2::new(31, 20));
+        assert!((layout.scale - 1.).abs() < f32::EPSILON);
+        assert_eq!(layout.offset, Vec2::new(4., 5.));
+        assert_eq!(
+            layer_size(Vec2::new(1000., 650.), Vec2::new(16., 16.)),
+            UVec2::new(62, 40)
+        );
+        assert_eq!(
+            layer_size(Vec2::new(10., 0.), Vec2::new(32., 32.)),
+            UVec2::ONE
+        );
+    }
+
+    #[test]
+    fn tile_at() {
+        let layout = layout(ResizePolicy::Letterbox, 2000., 400.);
+        // the bottom left of the window is in the empty space
+        assert_eq!(layout.tile_at(Vec2::new(10., 10.), 0_u16), None);
+        assert_eq!(
+            layout.tile_at(Vec2::new(680., 0.), 0_u16),
+            Some(UVec2::new(0, 24))
+        );
+        assert_eq!(
+            layout.tile_at(Vec2::new(700., 399.), 0_u16),
+            Some(UVec2::new(1, 0))
+        );
+        assert_eq!(layout.tile_at(Vec2::new(1320., 10.), 0_u16), None);
+        assert_eq!(layout.tile_at(Vec2::new(700., 10.), 1_u16), None);
+    }
+
+    #[test]
+    fn tile_at_a_moved_and_scaled_layer() {
+        let mut layout = layout(ResizePolicy::Letterbox, 2000., 400.);
+        // 10 by 10 tiles of 8 pixels, 16 pixels right and 32 pixels up from the first layer
+        layout.layers.push(LayerPlacement {
+            offset: Vec2::new(16., 32.),
+            tile_size: Vec2::new(8., 8.),
+            size: UVec2::new(10, 10),
+        });
+        // the window shows the tilemap at half its size
+        assert_eq!(
+            layout.tile_at(Vec2::new(688., 16.), 1_u16),
+            Some(UVec2::new(0, 9))
+        );
+        assert_eq!(
+            layout.tile_at(Vec2::new(700., 30.), 1_u16),
+            Some(UVec2::new(3, 6))
+        );
+        assert_eq!(
+            layout.tile_at(Vec2::new(700., 30.), 0_u16),
+            Some(UVec2::new(1, 23))
+        );
+        assert_eq!(layout.tile_at(Vec2::new(684., 16.), 1_u16), None);
+        assert_eq!(layout.tile_at(Vec2::new(728., 16.), 1_u16), None);
+    }
+}
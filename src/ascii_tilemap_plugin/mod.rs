@@ -6,12 +6,15 @@
 )]
 
 use self::{
+    cp437::to_cp437,
     draw_context::ActiveLayer,
-    render::{RenderLayers, TileRenderData},
+    layout::LayerPlacement,
+    render::{RenderLayers, TileBuffer, TileRenderData},
 };
 use bevy::{
     prelude::*,
     render::{camera::ScalingMode, render_resource::TextureUsages},
+    window::WindowResized,
 };
 use bevy_ecs_tilemap::{
     ChunkPos, ChunkSize, Map, MapQuery, MapSize, TextureSize, TileParent, TilePos, TileSize,
@@ -19,32 +22,101 @@ use bevy_ecs_tilemap::{
 
 pub use builder::{LayerDataBuilder, TilemapBuilder};
 pub use draw_context::DrawContext;
+pub use layout::{ResizePolicy, TilemapLayout};
+pub use render::RenderStats;
+pub use virtual_console::VirtualConsole;
 
 mod builder;
 pub mod color;
+pub mod cp437;
 pub mod draw_context;
 pub mod geometry;
+pub mod grid;
+pub mod layout;
 mod render;
+pub mod rich_text;
+pub mod terminal;
+pub mod virtual_console;
 
 #[derive(Clone, Hash, Debug, PartialEq, Eq, SystemLabel)]
 pub struct TilemapDrawing;
 
-pub struct AsciiTilemapPlugin;
+/// Where the layers are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Tiles of a `bevy_ecs_tilemap` map in a window
+    #[default]
+    Tilemap,
+    /// Characters of an ANSI terminal with truecolor, without a window
+    Terminal,
+    /// Nothing is shown, the `VirtualConsole` resource holds what would be
+    Virtual,
+}
+
+#[derive(Debug, Default)]
+pub struct AsciiTilemapPlugin {
+    pub backend: Backend,
+}
+
+impl AsciiTilemapPlugin {
+    #[must_use]
+    pub fn new(backend: Backend) -> Self {
+        Self { backend }
+    }
+}
 
 impl Plugin for AsciiTilemapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(bevy_ecs_tilemap::TilemapPlugin)
-            .add_system_set(
-                SystemSet::new()
-                    .label(TilemapDrawing)
-                    .with_system(process_command_buffer.before("render"))
-                    .with_system(render::render.label("render")),
-            )
-            .add_startup_system(setup.label("setup"))
-            .add_startup_stage("tile_setup", SystemStage::parallel())
-            .add_startup_system_to_stage("tile_setup", setup_tiles)
-            .add_system(set_texture_filters_to_nearest)
-            .insert_resource(ActiveLayer(0));
+        app.insert_resource(ActiveLayer(0))
+            .init_resource::<RenderStats>();
+        match self.backend {
+            Backend::Tilemap => {
+                app.add_plugin(bevy_ecs_tilemap::TilemapPlugin)
+                    .add_system_set(
+                        SystemSet::new()
+                            .label(TilemapDrawing)
+                            .with_system(process_command_buffer.before("diff"))
+                            .with_system(render::diff_render_layers.label("diff").before("render"))
+                            .with_system(render::render.label("render"))
+                            .with_system(render::show_visible_layers),
+                    )
+                    .add_startup_system(setup.label("setup"))
+                    .add_startup_stage("tile_setup", SystemStage::parallel())
+                    .add_startup_system_to_stage("tile_setup", setup_tiles)
+                    // tiles of layers rebuilt after a resize
+                    .add_system(setup_tiles.before(TilemapDrawing))
+                    .add_system(resize_tilemap.before(TilemapDrawing))
+                    .add_system(set_texture_filters_to_nearest);
+            }
+            Backend::Terminal => {
+                app.init_resource::<terminal::TerminalScreen>()
+                    .add_system_set(
+                        SystemSet::new()
+                            .label(TilemapDrawing)
+                            .with_system(process_command_buffer.before("render"))
+                            .with_system(terminal::render_terminal.label("render")),
+                    )
+                    .add_startup_system(setup_layers.label("setup"))
+                    .add_startup_system(terminal::setup_terminal)
+                    .add_system_to_stage(CoreStage::First, terminal::terminal_input);
+            }
+            Backend::Virtual => {
+                app.init_resource::<VirtualConsole>()
+                    .add_system_set(
+                        SystemSet::new()
+                            .label(TilemapDrawing)
+                            .with_system(process_command_buffer.before("diff"))
+                            // counts the tiles a tilemap would update
+                            .with_system(render::diff_render_layers.label("diff"))
+                            .with_system(
+                                virtual_console::update_virtual_console
+                                    .label("render")
+                                    .after(process_command_buffer),
+                            ),
+                    )
+                    .add_startup_system(setup_layers.label("setup"));
+            }
+        }
     }
 }
 
@@ -57,6 +129,9 @@ pub struct Layer {
     is_transparent: bool,
     is_background_transparent: bool,
     size: UVec2,
+    z_order: u16,
+    /// Hidden layers are still drawn to, they aren't shown
+    is_visible: bool,
     command_buffer: Vec<DrawCommand>,
 }
 
@@ -67,35 +142,227 @@ enum DrawCommand {
         y: u32,
         background: Color,
         foreground: Color,
-        glyph: char,
+        /// Index in the tilesheet
+        glyph: u8,
     },
     ClearLayer {
         color: Color,
     },
 }
 
+/// Marks the camera showing the tilemap
+#[derive(Component)]
+struct TilemapCamera;
+
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut map_query: MapQuery,
     tilemap_builder: Res<TilemapBuilder>,
+    windows: Res<Windows>,
 ) {
+    let (size, tile_size) = first_layer(&tilemap_builder);
+    let window_size = windows.get_primary().map_or_else(
+        || size.as_vec2() * tile_size,
+        |window| Vec2::new(window.width(), window.height()),
+    );
+    let mut layout =
+        TilemapLayout::new(tilemap_builder.resize_policy, window_size, size, tile_size);
+
     let mut camera_bundle = OrthographicCameraBundle::new_2d();
-    camera_bundle.orthographic_projection.scaling_mode = ScalingMode::FixedVertical;
-    camera_bundle.orthographic_projection.scale = 400.;
-    commands.spawn_bundle(camera_bundle);
+    fit_projection(&mut camera_bundle.orthographic_projection, &layout);
+    commands.spawn_bundle(camera_bundle).insert(TilemapCamera);
+
+    let sizes = layer_sizes(&tilemap_builder, &layout);
+    spawn_tilemap(
+        &mut commands,
+        &asset_server,
+        &mut map_query,
+        &tilemap_builder,
+        &sizes,
+    );
+    layout.layers = layer_placements(&tilemap_builder, &sizes);
+    commands.insert_resource(layout);
+}
 
+/// Spawns the layers letterboxed at the size of the first layer, without a tilemap or a window.
+/// They keep their size whatever the `ResizePolicy`.
+fn setup_layers(mut commands: Commands, tilemap_builder: Res<TilemapBuilder>) {
+    let (size, tile_size) = first_layer(&tilemap_builder);
+    let mut layout = TilemapLayout::new(
+        ResizePolicy::Letterbox,
+        size.as_vec2() * tile_size,
+        size,
+        tile_size,
+    );
+    let sizes = layer_sizes(&tilemap_builder, &layout);
+    let layers = tilemap_builder
+        .layers
+        .iter()
+        .zip(&sizes)
+        .map(|(layer, size)| layer.build_layer(*size))
+        .collect::<Vec<_>>();
+    let render_layers = new_render_layers(&layers);
+    commands.insert_resource(TileBuffer::new(&render_layers));
+    commands.insert_resource(render_layers);
+    spawn_layers(&mut commands, &tilemap_builder, &sizes);
+    layout.layers = layer_placements(&tilemap_builder, &sizes);
+    commands.insert_resource(layout);
+}
+
+/// Empty render layers of the layers, ordered by id
+fn new_render_layers(layers: &[Layer]) -> RenderLayers {
+    layers
+        .iter()
+        .flat_map(|layer| {
+            let len = (layer.size.x * layer.size.y) as usize;
+            // like the tilemap, transparent backgrounds don't have tiles
+            let background_len = if layer.is_background_transparent {
+                0
+            } else {
+                len
+            };
+            [
+                vec![TileRenderData::default(); background_len],
+                vec![TileRenderData::default(); len],
+            ]
+        })
+        .collect()
+}
+
+/// Follows the size of the window according to the `ResizePolicy`
+#[allow(clippy::too_many_arguments)]
+fn resize_tilemap(
+    mut commands: Commands,
+    mut resize_events: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    asset_server: Res<AssetServer>,
+    mut map_query: MapQuery,
+    tilemap_builder: Res<TilemapBuilder>,
+    mut tilemap_layout: ResMut<TilemapLayout>,
+    layer_entities: Res<LayerEntities>,
+    mut projections: Query<&mut OrthographicProjection, With<TilemapCamera>>,
+) {
+    let primary = match windows.get_primary() {
+        Some(window) => window.id(),
+        None => return,
+    };
+    let window_size = match resize_events
+        .iter()
+        .filter(|event| event.id == primary)
+        .last()
+    {
+        Some(event) => Vec2::new(event.width, event.height),
+        None => return,
+    };
+    if window_size.min_element() <= 0. {
+        // minimized, there is nothing to show
+        return;
+    }
+    let (size, tile_size) = first_layer(&tilemap_builder);
+    let mut layout =
+        TilemapLayout::new(tilemap_builder.resize_policy, window_size, size, tile_size);
+    projections.for_each_mut(|mut projection| fit_projection(&mut projection, &layout));
+
+    let sizes = layer_sizes(&tilemap_builder, &layout);
+    if layout.size != tilemap_layout.size {
+        info!("Resizing the tilemap to {}", layout.size);
+        map_query.despawn(&mut commands, 0_u16);
+        for entity in layer_entities.iter() {
+            commands.entity(*entity).despawn();
+        }
+        spawn_tilemap(
+            &mut commands,
+            &asset_server,
+            &mut map_query,
+            &tilemap_builder,
+            &sizes,
+        );
+    }
+    layout.layers = layer_placements(&tilemap_builder, &sizes);
+    *tilemap_layout = layout;
+}
+
+/// Size and tile size of the first layer, the others are aligned on it
+fn first_layer(tilemap_builder: &TilemapBuilder) -> (UVec2, Vec2) {
+    let layer = &tilemap_builder.layers[0];
+    (
+        layer.size.expect("size not set on first layer"),
+        layer.tile_size.expect("tile_size not set on first layer"),
+    )
+}
+
+/// Size in tiles of every layer for a layout, the first layer has the size of the layout
+/// and the others fill it from their offset with their scaled tiles, unless their size is fixed
+fn layer_sizes(tilemap_builder: &TilemapBuilder, layout: &TilemapLayout) -> Vec<UVec2> {
+    let tilemap_size = layout.size.as_vec2() * layout.tile_size;
+    tilemap_builder
+        .layers
+        .iter()
+        .enumerate()
+        .map(|(i, layer)| {
+            if i == 0 {
+                layout.size
+            } else if layer.is_fixed_size {
+                layer.size.expect("layer.size not set")
+            } else {
+                let available = (tilemap_size - layer.offset).max(Vec2::ZERO);
+                layout::layer_size(available, layer.scaled_tile_size())
+            }
+        })
+        .collect()
+}
+
+/// Where the layers of the given sizes are drawn, to find the tiles under the cursor
+fn layer_placements(tilemap_builder: &TilemapBuilder, sizes: &[UVec2]) -> Vec<LayerPlacement> {
+    tilemap_builder
+        .layers
+        .iter()
+        .zip(sizes)
+        .map(|(layer, size)| LayerPlacement {
+            offset: layer.offset,
+            tile_size: layer.scaled_tile_size(),
+            size: *size,
+        })
+        .collect()
+}
+
+/// Shows the whole window, the tilemap is scaled and centered by the layout
+fn fit_projection(projection: &mut OrthographicProjection, layout: &TilemapLayout) {
+    let half_size = layout.window_size / (2. * layout.scale);
+    projection.scaling_mode = ScalingMode::None;
+    projection.scale = 1.;
+    projection.left = -half_size.x;
+    projection.right = half_size.x;
+    projection.bottom = -half_size.y;
+    projection.top = half_size.y;
+}
+
+/// Spawns the layers of the tilemap with the given sizes, centered on the origin
+fn spawn_tilemap(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    map_query: &mut MapQuery,
+    tilemap_builder: &TilemapBuilder,
+    sizes: &[UVec2],
+) {
     let map_entity = commands.spawn().id();
     let mut map = Map::new(0_u16, map_entity);
 
     let mut render_layers = Vec::with_capacity(tilemap_builder.layers.len() * 2);
 
-    let mut build_layer = |layer_id, layer_settings, material_handle: Handle<Image>| {
+    let mut build_layer = |commands: &mut Commands,
+                           layer_id,
+                           layer_settings,
+                           material_handle: Handle<Image>,
+                           transform: Transform| {
         let (mut layer_builder, layer_entity) =
-            bevy_ecs_tilemap::LayerBuilder::new(&mut commands, layer_settings, 0_u16, layer_id);
+            bevy_ecs_tilemap::LayerBuilder::new(commands, layer_settings, 0_u16, layer_id);
         layer_builder.set_all(bevy_ecs_tilemap::TileBundle::default());
-        map_query.build_layer(&mut commands, layer_builder, material_handle);
-        map.add_layer(&mut commands, layer_id, layer_entity);
+        map_query.build_layer(commands, layer_builder, material_handle);
+        map.add_layer(commands, layer_id, layer_entity);
+        // replaces the transform ordering the layers by id
+        commands.entity(layer_entity).insert(transform);
 
         let render_layer = vec![
             TileRenderData::default();
@@ -108,8 +375,8 @@ fn setup(
     // always just use 1 chunk per layer since this is always going to be on screen anyway
     let map_size = MapSize(1, 1);
 
-    for layer_builder_data in &tilemap_builder.layers {
-        let layer_data = layer_builder_data.build_layer();
+    for (layer_builder_data, size) in tilemap_builder.layers.iter().zip(sizes) {
+        let layer_data = layer_builder_data.build_layer(*size);
 
         let tile_size = layer_builder_data.tile_size.expect("tile_size not set");
         let tilesheet_size = layer_builder_data
@@ -134,36 +401,34 @@ fn setup(
             // this should help iteration speed since we don't need to iterate as many tiles
             layer_settings.chunk_size = ChunkSize(0, 0);
             build_layer(
+                commands,
                 layer_data.background_id,
                 layer_settings,
                 texture_handle.clone(),
+                layer_transform(layer_builder_data, &layer_data, false),
             );
         } else {
             build_layer(
+                commands,
                 layer_data.background_id,
                 layer_settings,
                 texture_handle.clone(),
+                layer_transform(layer_builder_data, &layer_data, false),
             );
         }
-        build_layer(layer_data.foreground_id, layer_settings, texture_handle);
+        build_layer(
+            commands,
+            layer_data.foreground_id,
+            layer_settings,
+            texture_handle,
+            layer_transform(layer_builder_data, &layer_data, true),
+        );
     }
+    commands.insert_resource(TileBuffer::new(&render_layers));
     commands.insert_resource(render_layers as RenderLayers);
+    spawn_layers(commands, tilemap_builder, sizes);
 
-    let mut layer_entities = vec![];
-    for layer_builder_data in &tilemap_builder.layers {
-        let layer_data = layer_builder_data.build_layer();
-        // info!("layer_data {:?}", layer_data);
-        // because of borrow checker can't do this in the other loop
-        // can't borrow commands
-        let entity = commands.spawn().insert(layer_data).id();
-        layer_entities.push(entity);
-    }
-    commands.insert_resource(layer_entities as LayerEntities);
-
-    let size = tilemap_builder.layers[0]
-        .size
-        .expect("size not set on first layer")
-        .as_vec2();
+    let size = sizes[0].as_vec2();
     let tile_size = tilemap_builder.layers[0]
         .tile_size
         .expect("tile_size not set on first layer");
@@ -175,6 +440,32 @@ fn setup(
         .insert(GlobalTransform::default());
 }
 
+/// Where a render layer is drawn relative to the map, the foreground is just over the background
+fn layer_transform(
+    layer_builder: &LayerDataBuilder,
+    layer: &Layer,
+    is_foreground: bool,
+) -> Transform {
+    let z = f32::from(layer.z_order) * 2. + if is_foreground { 1. } else { 0. };
+    Transform {
+        translation: layer_builder.offset.extend(z),
+        scale: Vec3::new(layer_builder.scale, layer_builder.scale, 1.),
+        ..Default::default()
+    }
+}
+
+/// Spawns the `Layer` entities drawn by `DrawContext`, in the order of the builder
+fn spawn_layers(commands: &mut Commands, tilemap_builder: &TilemapBuilder, sizes: &[UVec2]) {
+    let mut layer_entities = vec![];
+    for (layer_builder_data, size) in tilemap_builder.layers.iter().zip(sizes) {
+        let layer_data = layer_builder_data.build_layer(*size);
+        // info!("layer_data {:?}", layer_data);
+        let entity = commands.spawn().insert(layer_data).id();
+        layer_entities.push(entity);
+    }
+    commands.insert_resource(layer_entities as LayerEntities);
+}
+
 #[derive(Component)]
 pub struct TileData {
     pub index: usize,
@@ -184,8 +475,9 @@ pub struct TileData {
 
 fn setup_tiles(
     mut commands: Commands,
-    tile_query: Query<(Entity, &TileParent, &TilePos)>,
+    tile_query: Query<(Entity, &TileParent, &TilePos), Without<TileData>>,
     layers: Query<&Layer>,
+    mut tile_buffer: ResMut<TileBuffer>,
 ) {
     let mut i = 0;
     tile_query.for_each(|(entity, tile_parent, pos)| {
@@ -197,57 +489,77 @@ fn setup_tiles(
             })
             .expect("layer not found");
         let index = (pos.1 * layer.size.x + pos.0) as usize;
+        tile_buffer.set_entity(tile_parent.layer_id as usize, index, entity);
         commands.entity(entity).insert(TileData {
             index,
             layer_id: tile_parent.layer_id as usize,
             chunk: tile_parent.chunk,
         });
     });
-    info!("TileData added to tiles {}", i);
+    if i > 0 {
+        info!("TileData added to tiles {}", i);
+    }
 }
 
-fn process_command_buffer(mut layers: Query<&mut Layer>, mut render_layers: ResMut<RenderLayers>) {
+fn process_command_buffer(
+    mut layers: Query<&mut Layer>,
+    mut render_layers: ResMut<RenderLayers>,
+    mut tile_buffer: ResMut<TileBuffer>,
+) {
     // puffin::profile_function!();
     layers.for_each_mut(|mut layer| {
-        // info!("buffer len: {}", layer.command_buffer.len());
-        for command in &layer.command_buffer {
-            match *command {
-                DrawCommand::DrawTile {
-                    x,
-                    y,
-                    background,
-                    foreground,
-                    glyph,
-                } => {
-                    let y = layer.size.y - y - 1;
-                    let index = (y * layer.size.x + x) as usize;
-
-                    if !layer.is_background_transparent {
-                        let background_tile = TileRenderData::new(background, 219 as char); // ASCII code 219 = █ ( Block, graphic character )
-                        render_layers[layer.background_id as usize][index] = background_tile;
-                    }
-
-                    let foreground_tile = TileRenderData::new(foreground, glyph);
-                    render_layers[layer.foreground_id as usize][index] = foreground_tile;
+        apply_commands(&mut layer, &mut render_layers, &mut tile_buffer);
+    });
+}
+
+/// Writes the commands of a layer to its render layers and empties its command buffer
+fn apply_commands(
+    layer: &mut Layer,
+    render_layers: &mut RenderLayers,
+    tile_buffer: &mut TileBuffer,
+) {
+    // info!("buffer len: {}", layer.command_buffer.len());
+    for command in &layer.command_buffer {
+        match *command {
+            DrawCommand::DrawTile {
+                x,
+                y,
+                background,
+                foreground,
+                glyph,
+            } => {
+                let y = layer.size.y - y - 1;
+                let index = (y * layer.size.x + x) as usize;
+
+                if !layer.is_background_transparent {
+                    let background_tile = TileRenderData::new(background, to_cp437('█'));
+                    render_layers[layer.background_id as usize][index] = background_tile;
+                    tile_buffer.mark(layer.background_id as usize, index);
                 }
-                DrawCommand::ClearLayer { color } => {
-                    for mut tile in &mut render_layers[layer.background_id as usize] {
-                        tile.glyph = if layer.is_transparent {
-                            0 // foreground and transparent backgrounds should be invisible after clear
-                        } else {
-                            219 // ASCII code 219 = █ ( Block, graphic character )
-                        };
-                        tile.color = color;
-                    }
-                    for mut tile in &mut render_layers[layer.foreground_id as usize] {
-                        tile.glyph = 0_u16;
-                        tile.color = color;
-                    }
+
+                let foreground_tile = TileRenderData::new(foreground, glyph);
+                render_layers[layer.foreground_id as usize][index] = foreground_tile;
+                tile_buffer.mark(layer.foreground_id as usize, index);
+            }
+            DrawCommand::ClearLayer { color } => {
+                // foreground and transparent backgrounds should be invisible after clear
+                let background_glyph = if layer.is_transparent {
+                    0
+                } else {
+                    to_cp437('█')
+                };
+                for (layer_id, glyph) in [
+                    (layer.background_id, background_glyph),
+                    (layer.foreground_id, 0),
+                ] {
+                    let layer_id = layer_id as usize;
+                    let tile = TileRenderData::new(color, glyph);
+                    tile_buffer.clear(layer_id, &mut render_layers[layer_id], tile);
                 }
             }
         }
-        layer.command_buffer.clear();
-    });
+    }
+    layer.command_buffer.clear();
 }
 
 // This assumes a single map with a single chunk per layer
@@ -283,3 +595,126 @@ pub fn set_texture_filters_to_nearest(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A map of 40 by 25 tiles of 32 pixels under a hud of tiles of 16 pixels,
+    /// a fixed minimap and a scaled overlay above the bottom 64 pixels
+    fn tilemap_builder(resize_policy: ResizePolicy) -> TilemapBuilder {
+        TilemapBuilder::new()
+            .resize_policy(resize_policy)
+            .with_layer(
+                LayerDataBuilder::new(0_u16)
+                    .size(40, 25)
+                    .tile_size(32., 32.),
+            )
+            .with_layer(LayerDataBuilder::new(1_u16).tile_size(16., 16.))
+            .with_layer(
+                LayerDataBuilder::new(2_u16)
+                    .size(80, 50)
+                    .tile_size(16., 16.)
+                    .scale(0.25)
+                    .offset(8., 104.)
+                    .is_fixed_size(true),
+            )
+            .with_layer(
+                LayerDataBuilder::new(3_u16)
+                    .tile_size(16., 16.)
+                    .scale(0.5)
+                    .offset(0., 64.),
+            )
+            .build()
+    }
+
+    fn sizes(resize_policy: ResizePolicy, window_width: f32, window_height: f32) -> Vec<UVec2> {
+        let tilemap_builder = tilemap_builder(resize_policy);
+        let (size, tile_size) = first_layer(&tilemap_builder);
+        let window_size = Vec2::new(window_width, window_height);
+        let layout = TilemapLayout::new(resize_policy, window_size, size, tile_size);
+        layer_sizes(&tilemap_builder, &layout)
+    }
+
+    #[test]
+    fn letterboxed_layer_sizes() {
+        let sizes = sizes(ResizePolicy::Letterbox, 2000., 400.);
+        assert_eq!(
+            sizes,
+            [
+                UVec2::new(40, 25),
+                UVec2::new(80, 50),
+                UVec2::new(80, 50),
+                // twice as many tiles as the hud, the 64 pixels below it are left out
+                UVec2::new(160, 92),
+            ]
+        );
+    }
+
+    #[test]
+    fn resized_layer_sizes() {
+        let sizes = sizes(ResizePolicy::Resize, 1000., 650.);
+        assert_eq!(
+            sizes,
+            [
+                UVec2::new(31, 20),
+                UVec2::new(62, 40),
+                UVec2::new(80, 50),
+                UVec2::new(124, 72),
+            ]
+        );
+    }
+
+    #[test]
+    fn placements_find_the_tiles_of_every_layer() {
+        let tilemap_builder = tilemap_builder(ResizePolicy::Letterbox);
+        let (size, tile_size) = first_layer(&tilemap_builder);
+        let mut layout = TilemapLayout::new(
+            ResizePolicy::Letterbox,
+            Vec2::new(1280., 800.),
+            size,
+            tile_size,
+        );
+        layout.layers = layer_placements(&tilemap_builder, &layer_sizes(&tilemap_builder, &layout));
+        let cursor_position = Vec2::new(12., 108.);
+        assert_eq!(
+            layout.tile_at(cursor_position, 0_u16),
+            Some(UVec2::new(0, 21))
+        );
+        assert_eq!(
+            layout.tile_at(cursor_position, 1_u16),
+            Some(UVec2::new(0, 43))
+        );
+        assert_eq!(
+            layout.tile_at(cursor_position, 2_u16),
+            Some(UVec2::new(1, 48))
+        );
+        assert_eq!(
+            layout.tile_at(cursor_position, 3_u16),
+            Some(UVec2::new(1, 86))
+        );
+    }
+
+    #[test]
+    fn layer_transform_places_the_render_layers() {
+        let builder = LayerDataBuilder::new(2_u16)
+            .offset(8., 104.)
+            .scale(0.25)
+            .z_order(5)
+            .clone();
+        let layer = builder.build_layer(UVec2::new(80, 50));
+        let background = layer_transform(&builder, &layer, false);
+        let foreground = layer_transform(&builder, &layer, true);
+        assert_eq!(background.translation, Vec3::new(8., 104., 10.));
+        // the foreground is just over its background
+        assert_eq!(foreground.translation, Vec3::new(8., 104., 11.));
+        assert_eq!(foreground.scale, Vec3::new(0.25, 0.25, 1.));
+
+        // without a z order the layers are ordered by id
+        let builder = LayerDataBuilder::new(3_u16);
+        let layer = builder.build_layer(UVec2::ONE);
+        let foreground = layer_transform(&builder, &layer, true);
+        assert_eq!(foreground.translation, Vec3::new(0., 0., 7.));
+        assert_eq!(foreground.scale, Vec3::ONE);
+    }
+}
@@ -1,4 +1,6 @@
-use super::TileData;
+use std::fmt;
+
+use super::{Layer, TileData};
 use bevy::{prelude::*, utils::HashSet};
 use bevy_ecs_tilemap::{Chunk, Tile};
 
@@ -10,36 +12,277 @@ pub struct TileRenderData {
     pub glyph: u16,
 }
 impl TileRenderData {
-    pub fn new(color: Color, glyph: char) -> Self {
+    pub fn new(color: Color, glyph: u8) -> Self {
         Self {
             color,
-            glyph: glyph as u16,
+            glyph: u16::from(glyph),
         }
     }
 }
 impl Default for TileRenderData {
     fn default() -> Self {
-        Self::new(Color::BLACK, 0 as char)
+        Self::new(Color::BLACK, 0)
     }
 }
 
+/// The render layers as the tiles show them, `render` only updates the tiles that differ.
+/// The layers are drawn from scratch every frame but few tiles change between two frames.
+/// Only the tiles written since the last update are compared, see `mark` and `clear`.
+#[derive(Debug, Default)]
+pub struct TileBuffer {
+    /// `None` until the tile shows something
+    shown: Vec<Vec<Option<TileRenderData>>>,
+    entities: Vec<Vec<Option<Entity>>>,
+    /// Render layer and index of the tiles written since the last update
+    written: Vec<(usize, usize)>,
+    is_written: Vec<Vec<bool>>,
+    /// Every tile of a render layer cleared to a new tile is compared
+    is_cleared: Vec<bool>,
+    /// The tile a render layer was last cleared to, `None` until it's cleared
+    cleared_to: Vec<Option<TileRenderData>>,
+    /// Index of the tiles drawn since the render layer was cleared, the others show `cleared_to`
+    drawn: Vec<Vec<usize>>,
+    is_drawn: Vec<Vec<bool>>,
+    /// Render layer and index of the tiles that changed in the last frame
+    changes: Vec<(usize, usize)>,
+    /// Number of tiles compared by the last update
+    compared: usize,
+}
+
+impl TileBuffer {
+    #[must_use]
+    pub fn new(render_layers: &RenderLayers) -> Self {
+        Self {
+            shown: render_layers
+                .iter()
+                .map(|layer| vec![None; layer.len()])
+                .collect(),
+            entities: render_layers
+                .iter()
+                .map(|layer| vec![None; layer.len()])
+                .collect(),
+            written: vec![],
+            is_written: render_layers
+                .iter()
+                .map(|layer| vec![false; layer.len()])
+                .collect(),
+            // nothing is shown yet
+            is_cleared: vec![true; render_layers.len()],
+            cleared_to: vec![None; render_layers.len()],
+            drawn: vec![vec![]; render_layers.len()],
+            is_drawn: render_layers
+                .iter()
+                .map(|layer| vec![false; layer.len()])
+                .collect(),
+            changes: vec![],
+            compared: 0,
+        }
+    }
+
+    /// Marks a tile of a render layer as drawn, it's compared by the next update
+    pub fn mark(&mut self, layer_id: usize, index: usize) {
+        if let Some(is_drawn) = self
+            .is_drawn
+            .get_mut(layer_id)
+            .and_then(|layer| layer.get_mut(index))
+        {
+            if !*is_drawn {
+                *is_drawn = true;
+                self.drawn[layer_id].push(index);
+            }
+        }
+        self.mark_written(layer_id, index);
+    }
+
+    fn mark_written(&mut self, layer_id: usize, index: usize) {
+        if self.is_cleared.get(layer_id) != Some(&false) {
+            return;
+        }
+        if let Some(is_written) = self.is_written[layer_id].get_mut(index) {
+            if !*is_written {
+                *is_written = true;
+                self.written.push((layer_id, index));
+            }
+        }
+    }
+
+    /// Clears a render layer to a tile. When it was last cleared to the same tile, only the tiles
+    /// drawn since are reset and compared: a tile drawn again the same before the next update
+    /// doesn't change. A new tile resets and compares the whole layer.
+    pub fn clear(
+        &mut self,
+        layer_id: usize,
+        render_layer: &mut [TileRenderData],
+        tile: TileRenderData,
+    ) {
+        if self.cleared_to.get(layer_id) == Some(&Some(tile)) {
+            let mut drawn = std::mem::take(&mut self.drawn[layer_id]);
+            for &index in &drawn {
+                self.is_drawn[layer_id][index] = false;
+                render_layer[index] = tile;
+                self.mark_written(layer_id, index);
+            }
+            drawn.clear();
+            self.drawn[layer_id] = drawn;
+            return;
+        }
+        render_layer.fill(tile);
+        if let Some(cleared_to) = self.cleared_to.get_mut(layer_id) {
+            *cleared_to = Some(tile);
+            self.is_cleared[layer_id] = true;
+            self.drawn[layer_id].clear();
+            self.is_drawn[layer_id].fill(false);
+        }
+    }
+
+    /// The tile entity showing a tile of a render layer, it's updated by the next render
+    pub fn set_entity(&mut self, layer_id: usize, index: usize, entity: Entity) {
+        if let Some(tile) = self
+            .entities
+            .get_mut(layer_id)
+            .and_then(|layer| layer.get_mut(index))
+        {
+            *tile = Some(entity);
+            self.shown[layer_id][index] = None;
+            self.mark_written(layer_id, index);
+        }
+    }
+
+    /// Compares the written tiles to what the tiles show, the differences are the new changes
+    pub fn update(&mut self, render_layers: &RenderLayers) {
+        self.changes.clear();
+        self.compared = 0;
+        for (layer_id, layer) in render_layers.iter().enumerate() {
+            if self.is_cleared.get(layer_id) == Some(&true) {
+                self.compared += layer.len();
+                for (index, tile) in layer.iter().enumerate() {
+                    self.compare(layer_id, index, *tile);
+                }
+            }
+        }
+        for i in 0..self.written.len() {
+            let (layer_id, index) = self.written[i];
+            self.is_written[layer_id][index] = false;
+            if !self.is_cleared[layer_id] {
+                self.compared += 1;
+                self.compare(layer_id, index, render_layers[layer_id][index]);
+            }
+        }
+        self.written.clear();
+        self.is_cleared.fill(false);
+    }
+
+    fn compare(&mut self, layer_id: usize, index: usize, tile: TileRenderData) {
+        if let Some(shown) = self.shown[layer_id].get_mut(index) {
+            if *shown != Some(tile) {
+                *shown = Some(tile);
+                self.changes.push((layer_id, index));
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn changes(&self) -> &[(usize, usize)] {
+        &self.changes
+    }
+
+    #[must_use]
+    pub fn compared(&self) -> usize {
+        self.compared
+    }
+
+    #[must_use]
+    pub fn entity(&self, layer_id: usize, index: usize) -> Option<Entity> {
+        self.entities.get(layer_id)?.get(index).copied().flatten()
+    }
+
+    /// Number of tiles of every render layer
+    #[must_use]
+    pub fn tile_count(&self) -> usize {
+        self.shown.iter().map(Vec::len).sum()
+    }
+}
+
+/// Tiles compared and tiles changed by every frame so far, a full render touches every tile
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RenderStats {
+    pub frames: u32,
+    pub tiles: usize,
+    pub tiles_compared: usize,
+    pub tiles_changed: usize,
+}
+
+impl RenderStats {
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn tiles_compared_per_frame(&self) -> f32 {
+        if self.frames == 0 {
+            return 0.;
+        }
+        self.tiles_compared as f32 / self.frames as f32
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn tiles_changed_per_frame(&self) -> f32 {
+        if self.frames == 0 {
+            return 0.;
+        }
+        self.tiles_changed as f32 / self.frames as f32
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tiles_per_frame = if self.frames == 0 {
+            0
+        } else {
+            self.tiles / self.frames as usize
+        };
+        write!(
+            f,
+            "tiles compared per frame: {:.1}, changed: {:.1} of {}",
+            self.tiles_compared_per_frame(),
+            self.tiles_changed_per_frame(),
+            tiles_per_frame
+        )
+    }
+}
+
+/// Finds the tiles changed by the draw commands of this frame
+pub fn diff_render_layers(
+    render_layers: Res<RenderLayers>,
+    mut tile_buffer: ResMut<TileBuffer>,
+    mut stats: ResMut<RenderStats>,
+) {
+    // puffin::profile_function!();
+    tile_buffer.update(&render_layers);
+    stats.frames += 1;
+    stats.tiles += tile_buffer.tile_count();
+    stats.tiles_compared += tile_buffer.compared();
+    stats.tiles_changed += tile_buffer.changes().len();
+}
+
 pub fn render(
     mut chunk_query: Query<&mut Chunk>,
     mut tile_query: Query<(&mut Tile, &TileData)>,
     render_layers: Res<RenderLayers>,
+    tile_buffer: Res<TileBuffer>,
 ) {
     // puffin::profile_function!();
     let mut chunks = HashSet::default();
-    tile_query.for_each_mut(|(mut tile, tile_data)| {
-        let tile_render_data = &render_layers[tile_data.layer_id][tile_data.index];
-        if tile.texture_index != tile_render_data.glyph as u16
-            || tile.color != tile_render_data.color
-        {
-            tile.texture_index = tile_render_data.glyph as u16;
+    for &(layer_id, index) in tile_buffer.changes() {
+        let entity = match tile_buffer.entity(layer_id, index) {
+            Some(entity) => entity,
+            None => continue,
+        };
+        if let Ok((mut tile, tile_data)) = tile_query.get_mut(entity) {
+            let tile_render_data = &render_layers[layer_id][index];
+            tile.texture_index = tile_render_data.glyph;
             tile.color = tile_render_data.color;
             chunks.insert(tile_data.chunk);
         }
-    });
+    }
     {
         // puffin::profile_scope!("chunk needs remesh");
         for chunk_entity in chunks.drain() {
@@ -49,3 +292,271 @@ pub fn render(
         }
     }
 }
+
+/// Hides the chunks of the hidden layers, only when a layer is shown or hidden
+/// or when the chunks are spawned again after a resize
+pub fn show_visible_layers(
+    layers: Query<&Layer>,
+    mut chunks: Query<(&Chunk, &mut Visibility)>,
+    new_chunks: Query<(), Added<Chunk>>,
+    mut last_visibility: Local<Vec<(u16, bool)>>,
+) {
+    // puffin::profile_function!();
+    let mut visibility = layers
+        .iter()
+        .map(|layer| (layer.foreground_id, layer.is_visible))
+        .collect::<Vec<_>>();
+    visibility.sort_unstable();
+    if *last_visibility == visibility && new_chunks.iter().next().is_none() {
+        return;
+    }
+    *last_visibility = visibility;
+    for layer in layers.iter() {
+        for (chunk, mut visibility) in chunks.iter_mut() {
+            let layer_id = chunk.settings.layer_id;
+            if (layer_id == layer.background_id || layer_id == layer.foreground_id)
+                && visibility.is_visible != layer.is_visible
+            {
+                visibility.is_visible = layer.is_visible;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate test;
+
+    use super::*;
+    use test::Bencher;
+
+    fn render_layers() -> RenderLayers {
+        vec![vec![TileRenderData::default(); 4], vec![]]
+    }
+
+    #[test]
+    fn only_changed_tiles() {
+        let mut render_layers = render_layers();
+        let mut tile_buffer = TileBuffer::new(&render_layers);
+        assert_eq!(tile_buffer.tile_count(), 4);
+        // nothing is shown at first
+        tile_buffer.update(&render_layers);
+        assert_eq!(tile_buffer.changes().len(), 4);
+
+        tile_buffer.update(&render_layers);
+        assert!(tile_buffer.changes().is_empty());
+
+        // cleared and drawn again the same
+        render_layers[0][2] = TileRenderData::new(Color::RED, b'@');
+        render_layers[0][2] = TileRenderData::default();
+        render_layers[0][3] = TileRenderData::new(Color::RED, b'@');
+        tile_buffer.mark(0, 2);
+        tile_buffer.mark(0, 3);
+        tile_buffer.mark(0, 3);
+        tile_buffer.update(&render_layers);
+        assert_eq!(tile_buffer.changes(), &[(0, 3)]);
+    }
+
+    #[test]
+    fn only_written_tiles_are_compared() {
+        let mut render_layers = render_layers();
+        let mut tile_buffer = TileBuffer::new(&render_layers);
+        tile_buffer.update(&render_layers);
+
+        render_layers[0][1] = TileRenderData::new(Color::RED, b'@');
+        tile_buffer.update(&render_layers);
+        assert!(tile_buffer.changes().is_empty());
+
+        assert_eq!(tile_buffer.compared(), 0);
+
+        // cleared to a new tile, the whole layer is compared
+        tile_buffer.clear(
+            0,
+            &mut render_layers[0],
+            TileRenderData::new(Color::RED, b'#'),
+        );
+        tile_buffer.update(&render_layers);
+        assert_eq!(tile_buffer.changes(), &[(0, 0), (0, 1), (0, 2), (0, 3)]);
+        assert_eq!(tile_buffer.compared(), 4);
+        tile_buffer.update(&render_layers);
+        assert!(tile_buffer.changes().is_empty());
+        assert_eq!(tile_buffer.compared(), 0);
+    }
+
+    #[test]
+    fn cleared_and_drawn_again() {
+        /// Clears the layer and draws the player on a tile
+        fn draw_frame(tile_buffer: &mut TileBuffer, render_layers: &mut RenderLayers, at: usize) {
+            tile_buffer.clear(0, &mut render_layers[0], TileRenderData::default());
+            render_layers[0][at] = TileRenderData::new(Color::WHITE, b'@');
+            tile_buffer.mark(0, at);
+            tile_buffer.update(render_layers);
+        }
+        let mut render_layers = render_layers();
+        let mut tile_buffer = TileBuffer::new(&render_layers);
+        draw_frame(&mut tile_buffer, &mut render_layers, 0);
+        assert_eq!(tile_buffer.changes().len(), 4);
+        assert_eq!(tile_buffer.compared(), 4);
+
+        // only the player is reset and compared, it's drawn again the same
+        draw_frame(&mut tile_buffer, &mut render_layers, 0);
+        assert!(tile_buffer.changes().is_empty());
+        assert_eq!(tile_buffer.compared(), 1);
+
+        draw_frame(&mut tile_buffer, &mut render_layers, 1);
+        assert_eq!(tile_buffer.changes(), &[(0, 0), (0, 1)]);
+        assert_eq!(tile_buffer.compared(), 2);
+        assert_eq!(render_layers[0][0], TileRenderData::default());
+        assert_eq!(render_layers[0][1], TileRenderData::new(Color::WHITE, b'@'));
+    }
+
+    #[test]
+    fn new_entities_are_updated() {
+        let render_layers = render_layers();
+        let mut tile_buffer = TileBuffer::new(&render_layers);
+        tile_buffer.update(&render_layers);
+        assert_eq!(tile_buffer.entity(0, 1), None);
+
+        let entity = Entity::from_raw(7);
+        tile_buffer.set_entity(0, 1, entity);
+        tile_buffer.set_entity(1, 0, entity);
+        assert_eq!(tile_buffer.entity(0, 1), Some(entity));
+        assert_eq!(tile_buffer.entity(1, 0), None);
+        tile_buffer.update(&render_layers);
+        assert_eq!(tile_buffer.changes(), &[(0, 1)]);
+    }
+
+    #[test]
+    fn stats() {
+        let stats = RenderStats {
+            frames: 4,
+            tiles: 400,
+            tiles_compared: 30,
+            tiles_changed: 10,
+        };
+        assert!((stats.tiles_compared_per_frame() - 7.5).abs() < f32::EPSILON);
+        assert!((stats.tiles_changed_per_frame() - 2.5).abs() < f32::EPSILON);
+        assert_eq!(
+            stats.to_string(),
+            "tiles compared per frame: 7.5, changed: 2.5 of 100"
+        );
+        assert_eq!(
+            RenderStats::default().to_string(),
+            "tiles compared per frame: 0.0, changed: 0.0 of 0"
+        );
+    }
+
+    // `cargo bench render` compares a frame of the dungeon rendered by comparing every tile
+    // with one rendered from the change list
+
+    /// The render layers of the dungeon: the map and entities are 40x25,
+    /// the hud and diagnostic layers 80x50, each with a background and a foreground
+    fn dungeon_render_layers() -> RenderLayers {
+        [1000, 1000, 1000, 1000, 4000, 4000, 4000, 4000]
+            .iter()
+            .map(|len| vec![TileRenderData::default(); *len])
+            .collect()
+    }
+
+    /// A tile entity for every tile, they show the render layers
+    fn tile_world() -> World {
+        let render_layers = dungeon_render_layers();
+        let mut tile_buffer = TileBuffer::new(&render_layers);
+        let mut world = World::new();
+        let chunk = world.spawn().id();
+        for (layer_id, layer) in render_layers.iter().enumerate() {
+            for index in 0..layer.len() {
+                let entity = world
+                    .spawn()
+                    .insert(Tile::default())
+                    .insert(TileData {
+                        index,
+                        layer_id,
+                        chunk,
+                    })
+                    .id();
+                tile_buffer.set_entity(layer_id, index, entity);
+            }
+        }
+        world.insert_resource(render_layers);
+        world.insert_resource(tile_buffer);
+        world.insert_resource(RenderStats::default());
+        world
+    }
+
+    /// A frame like the dungeon draws them: every layer is cleared, the map is drawn again
+    /// the same and the player takes a step on the entities layer, two tiles change
+    fn step(world: &mut World, turn: usize) {
+        world.resource_scope(|world, mut render_layers: Mut<RenderLayers>| {
+            let mut tile_buffer = world
+                .get_resource_mut::<TileBuffer>()
+                .expect("no tile buffer");
+            for (layer_id, layer) in render_layers.iter_mut().enumerate() {
+                tile_buffer.clear(layer_id, layer, TileRenderData::default());
+            }
+            for layer_id in [0, 1] {
+                for index in 0..render_layers[layer_id].len() {
+                    render_layers[layer_id][index] = TileRenderData::new(Color::GRAY, b'#');
+                    tile_buffer.mark(layer_id, index);
+                }
+            }
+            let player = turn % 1000;
+            render_layers[3][player] = TileRenderData::new(Color::WHITE, b'@');
+            tile_buffer.mark(3, player);
+        });
+    }
+
+    /// How `render` worked before the change list
+    fn render_every_tile(
+        mut chunk_query: Query<&mut Chunk>,
+        mut tile_query: Query<(&mut Tile, &TileData)>,
+        render_layers: Res<RenderLayers>,
+    ) {
+        let mut chunks = HashSet::default();
+        tile_query.for_each_mut(|(mut tile, tile_data)| {
+            let tile_render_data = &render_layers[tile_data.layer_id][tile_data.index];
+            if tile.texture_index != tile_render_data.glyph || tile.color != tile_render_data.color
+            {
+                tile.texture_index = tile_render_data.glyph;
+                tile.color = tile_render_data.color;
+                chunks.insert(tile_data.chunk);
+            }
+        });
+        for chunk_entity in chunks.drain() {
+            if let Ok(mut chunk) = chunk_query.get_mut(chunk_entity) {
+                chunk.needs_remesh = true;
+            }
+        }
+    }
+
+    fn bench_stage(b: &mut Bencher, mut stage: SystemStage) {
+        let mut world = tile_world();
+        // everything is shown once before measuring
+        step(&mut world, 0);
+        stage.run(&mut world);
+        let mut turn = 0;
+        b.iter(|| {
+            turn += 1;
+            step(&mut world, turn);
+            stage.run(&mut world);
+        });
+    }
+
+    #[bench]
+    fn render_comparing_every_tile(b: &mut Bencher) {
+        bench_stage(
+            b,
+            SystemStage::single_threaded().with_system(render_every_tile),
+        );
+    }
+
+    #[bench]
+    fn render_the_change_list(b: &mut Bencher) {
+        bench_stage(
+            b,
+            SystemStage::single_threaded()
+                .with_system(diff_render_layers.label("diff"))
+                .with_system(render.after("diff")),
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+
+/// A glyph of a text parsed by `parse`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RichChar {
+    pub glyph: char,
+    /// `None` uses the default foreground of the text
+    pub foreground: Option<Color>,
+}
+
+impl RichChar {
+    fn new(glyph: char, foreground: Option<Color>) -> Self {
+        Self { glyph, foreground }
+    }
+}
+
+/// Where the lines of a text are placed in their box
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    /// Offset of a line of `len` glyphs in a box `width` glyphs wide
+    #[must_use]
+    pub fn offset(self, width: usize, len: usize) -> usize {
+        let space = width.saturating_sub(len);
+        match self {
+            TextAlign::Left => 0,
+            TextAlign::Center => space / 2,
+            TextAlign::Right => space,
+        }
+    }
+}
+
+/// Colors that can be named in the markup
+const COLOR_NAMES: &[(&str, Color)] = &[
+    ("black", Color::BLACK),
+    ("white", Color::WHITE),
+    ("gray", Color::GRAY),
+    ("dark_gray", Color::DARK_GRAY),
+    ("red", Color::RED),
+    ("green", Color::GREEN),
+    ("blue", Color::BLUE),
+    ("yellow", Color::YELLOW),
+    ("orange", Color::ORANGE),
+    ("gold", Color::GOLD),
+    ("cyan", Color::CYAN),
+    ("purple", Color::PURPLE),
+    ("pink", Color::PINK),
+];
+
+#[must_use]
+pub fn color_by_name(name: &str) -> Option<Color> {
+    COLOR_NAMES
+        .iter()
+        .find(|(color_name, _)| *color_name == name)
+        .map(|(_, color)| *color)
+}
+
+/// The glyphs of a text without markup, they use the default foreground
+#[must_use]
+pub fn plain(text: &str) -> Vec<RichChar> {
+    text.chars().map(|c| RichChar::new(c, None)).collect()
+}
+
+/// Parses inline color markup, `#[red]` changes the foreground and `#[]` goes back to the default.
+/// Anything that isn't a known tag is kept as text.
+#[must_use]
+pub fn parse(text: &str) -> Vec<RichChar> {
+    let mut result = Vec::new();
+    let mut foreground = None;
+    let mut rest = text;
+    while let Some(start) = rest.find("#[") {
+        result.extend(rest[..start].chars().map(|c| RichChar::new(c, foreground)));
+        let after = &rest[start + 2..];
+        let tag = after.find(']').and_then(|end| {
+            let name = &after[..end];
+            let color = if name.is_empty() {
+                Some(None)
+            } else {
+                color_by_name(name).map(Some)
+            };
+            color.map(|color| (color, &after[end + 1..]))
+        });
+        if let Some((color, remaining)) = tag {
+            foreground = color;
+            rest = remaining;
+        } else {
+            result.push(RichChar::new('#', foreground));
+            result.push(RichChar::new('[', foreground));
+            rest = after;
+        }
+    }
+    result.extend(rest.chars().map(|c| RichChar::new(c, foreground)));
+    result
+}
+
+/// Splits a text into lines of at most `width` glyphs, breaking between words when possible.
+/// Line breaks in the text are kept, runs of spaces are collapsed into one
+/// and the spaces at the start and the end of a line are dropped.
+#[must_use]
+pub fn wrap(text: &[RichChar], width: usize) -> Vec<Vec<RichChar>> {
+    if width == 0 {
+        return vec![];
+    }
+    let mut lines = Vec::new();
+    for paragraph in text.split(|c| c.glyph == '\n') {
+        let mut line = Vec::new();
+        for mut word in paragraph
+            .split(|c| c.glyph == ' ')
+            .filter(|word| !word.is_empty())
+        {
+            loop {
+                let separator = usize::from(!line.is_empty());
+                if line.len() + separator + word.len() <= width {
+                    if separator > 0 {
+                        line.push(RichChar::new(' ', None));
+                    }
+                    line.extend_from_slice(word);
+                    break;
+                }
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                    continue;
+                }
+                // the word doesn't fit on a line of its own
+                let (head, tail) = word.split_at(width);
+                lines.push(head.to_vec());
+                word = tail;
+                if word.is_empty() {
+                    break;
+                }
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &[RichChar]) -> String {
+        line.iter().map(|c| c.glyph).collect()
+    }
+
+    fn texts(lines: &[Vec<RichChar>]) -> Vec<String> {
+        lines.iter().map(|line| text(line)).collect()
+    }
+
+    #[test]
+    fn plain_text() {
+        let parsed = parse("Hello");
+        assert_eq!(text(&parsed), "Hello");
+        assert!(parsed.iter().all(|c| c.foreground.is_none()));
+    }
+
+    #[test]
+    fn colors() {
+        let parsed = parse("#[red]Orc#[] hits you for #[yellow]3#[]");
+        assert_eq!(text(&parsed), "Orc hits you for 3");
+        let colors = parsed.iter().map(|c| c.foreground).collect::<Vec<_>>();
+        assert_eq!(&colors[..3], &[Some(Color::RED); 3]);
+        assert!(colors[3..17].iter().all(Option::is_none));
+        assert_eq!(colors[17], Some(Color::YELLOW));
+    }
+
+    #[test]
+    fn unknown_tags_are_text() {
+        assert_eq!(text(&parse("#[sparkly]a#[b")), "#[sparkly]a#[b");
+        let parsed = parse("#[green]#[x]");
+        assert_eq!(text(&parsed), "#[x]");
+        assert!(parsed.iter().all(|c| c.foreground == Some(Color::GREEN)));
+    }
+
+    #[test]
+    fn wrap_words() {
+        let lines = wrap(&parse("the orc hits you  hard"), 10);
+        assert_eq!(texts(&lines), vec!["the orc", "hits you", "hard"]);
+        assert_eq!(
+            texts(&wrap(&parse("line\n\nbreaks"), 10)),
+            vec!["line", "", "breaks"]
+        );
+        assert!(wrap(&parse("anything"), 0).is_empty());
+    }
+
+    #[test]
+    fn wrap_collapses_spaces() {
+        assert_eq!(texts(&wrap(&plain("  a   b  "), 10)), vec!["a b"]);
+        assert_eq!(texts(&wrap(&plain("ab    cd"), 3)), vec!["ab", "cd"]);
+    }
+
+    #[test]
+    fn wrap_long_words() {
+        let lines = wrap(&parse("a dragonslayer"), 5);
+        assert_eq!(texts(&lines), vec!["a", "drago", "nslay", "er"]);
+    }
+
+    #[test]
+    fn wrap_exact_fit() {
+        let lines = wrap(&plain("abc defg hi"), 8);
+        assert_eq!(texts(&lines), vec!["abc defg", "hi"]);
+        let lines = wrap(&plain("abcd"), 4);
+        assert_eq!(texts(&lines), vec!["abcd"]);
+    }
+
+    #[test]
+    fn wrap_counts_chars() {
+        // multi-byte glyphs take a single cell
+        let lines = wrap(&plain("épée ░░░ ☺☺☺☺☺"), 4);
+        assert_eq!(texts(&lines), vec!["épée", "░░░", "☺☺☺☺", "☺"]);
+        assert_eq!(plain("#[red]é").len(), 7);
+    }
+
+    #[test]
+    fn align() {
+        assert_eq!(TextAlign::Left.offset(10, 4), 0);
+        assert_eq!(TextAlign::Center.offset(10, 4), 3);
+        assert_eq!(TextAlign::Right.offset(10, 4), 6);
+        // lines that don't fit start at the left of the box
+        assert_eq!(TextAlign::Right.offset(3, 4), 0);
+        assert_eq!(TextAlign::Center.offset(4, 4), 0);
+    }
+
+    #[test]
+    fn wrap_keeps_colors() {
+        let lines = wrap(&parse("#[red]big orc#[] hits"), 4);
+        assert_eq!(texts(&lines), vec!["big", "orc", "hits"]);
+        assert!(lines[1].iter().all(|c| c.foreground == Some(Color::RED)));
+        assert!(lines[2].iter().all(|c| c.foreground.is_none()));
+    }
+}
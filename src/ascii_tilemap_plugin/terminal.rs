@@ -0,0 +1,341 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use bevy::{
+    app::AppExit,
+    input::{keyboard::KeyboardInput, ElementState},
+    prelude::*,
+};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode as TerminalKeyCode, KeyModifiers},
+    queue,
+    style::{self, Print, SetBackgroundColor, SetForegroundColor},
+    terminal,
+};
+
+use super::{
+    grid::{compose, Grid},
+    render::RenderLayers,
+    Layer, LayerEntities,
+};
+
+/// The cells on the terminal, only the cells that changed are written
+#[derive(Debug, Default)]
+pub struct TerminalScreen {
+    grid: Grid,
+}
+
+/// Restores the terminal when the app is dropped, even after a panic
+pub struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        // nothing left to report the errors to
+        let _ = crossterm::execute!(
+            stdout,
+            style::ResetColor,
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Switches to the alternate screen, `TerminalGuard` switches back
+pub fn setup_terminal(mut commands: Commands) {
+    if let Err(e) = terminal::enable_raw_mode().and_then(|()| {
+        crossterm::execute!(
+            io::stdout(),
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(terminal::ClearType::All)
+        )
+    }) {
+        panic!("failed to set up the terminal: {:?}", e);
+    }
+    commands.insert_resource(TerminalGuard);
+}
+
+pub fn render_terminal(
+    layers: Query<&Layer>,
+    layer_entities: Res<LayerEntities>,
+    render_layers: Res<RenderLayers>,
+    mut screen: ResMut<TerminalScreen>,
+) {
+    // puffin::profile_function!();
+    let layers = layer_entities
+        .iter()
+        .filter_map(|entity| layers.get(*entity).ok())
+        .collect::<Vec<_>>();
+    let grid = compose(&layers, &render_layers);
+    if let Err(e) = draw(&mut io::stdout().lock(), &grid, &screen.grid) {
+        warn!("failed to draw to the terminal: {:?}", e);
+    }
+    screen.grid = grid;
+}
+
+/// Writes the cells that differ from the previous grid, everything when the size changed
+#[allow(clippy::cast_possible_truncation)]
+fn draw<W: Write>(out: &mut W, grid: &Grid, previous: &Grid) -> io::Result<()> {
+    let is_redrawn = grid.size != previous.size;
+    if is_redrawn {
+        queue!(out, terminal::Clear(terminal::ClearType::All))?;
+    }
+    let mut colors = None;
+    for (i, cell) in grid.cells.iter().enumerate() {
+        if !is_redrawn && previous.cells[i] == *cell {
+            continue;
+        }
+        let (x, y) = (i as u32 % grid.size.x, i as u32 / grid.size.x);
+        queue!(out, cursor::MoveTo(x as u16, y as u16))?;
+        if colors != Some((cell.foreground, cell.background)) {
+            queue!(
+                out,
+                SetForegroundColor(terminal_color(cell.foreground)),
+                SetBackgroundColor(terminal_color(cell.background))
+            )?;
+            colors = Some((cell.foreground, cell.background));
+        }
+        queue!(out, Print(cell.glyph))?;
+    }
+    out.flush()
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn terminal_color(color: Color) -> style::Color {
+    let [r, g, b, _] = color.as_rgba_f32();
+    let channel = |value: f32| (value.clamp(0., 1.) * 255.).round() as u8;
+    style::Color::Rgb {
+        r: channel(r),
+        g: channel(g),
+        b: channel(b),
+    }
+}
+
+/// Sends the keys pressed in the terminal as keyboard input, Ctrl+C quits
+pub fn terminal_input(
+    mut keyboard_input_events: EventWriter<KeyboardInput>,
+    mut app_exit_events: EventWriter<AppExit>,
+    mut screen: ResMut<TerminalScreen>,
+    mut held_keys: Local<Vec<KeyCode>>,
+) {
+    // puffin::profile_function!();
+    let mut pressed_keys = vec![];
+    while let Ok(true) = event::poll(Duration::ZERO) {
+        match event::read() {
+            Ok(Event::Key(key)) => {
+                if key.code == TerminalKeyCode::Char('c')
+                    && key.modifiers.contains(KeyModifiers::CONTROL)
+                {
+                    app_exit_events.send(AppExit);
+                } else if let Some(key_code) = key_code(key.code) {
+                    pressed_keys.push(key_code);
+                }
+            }
+            // the screen may have been cleared, everything is drawn again
+            Ok(Event::Resize(..)) => screen.grid = Grid::default(),
+            Ok(Event::Mouse(..)) => {}
+            Err(e) => {
+                warn!("failed to read the terminal events: {:?}", e);
+                break;
+            }
+        }
+    }
+    for event in key_events(&mut held_keys, pressed_keys) {
+        keyboard_input_events.send(event);
+    }
+}
+
+/// Terminals don't report releases, the keys pressed in a frame are released in the next one
+/// so both `Input::pressed` and `Input::just_pressed` see them
+fn key_events(held_keys: &mut Vec<KeyCode>, pressed_keys: Vec<KeyCode>) -> Vec<KeyboardInput> {
+    let released_keys = std::mem::replace(held_keys, pressed_keys);
+    released_keys
+        .into_iter()
+        .map(|key_code| (key_code, ElementState::Released))
+        .chain(
+            held_keys
+                .iter()
+                .map(|key_code| (*key_code, ElementState::Pressed)),
+        )
+        .map(|(key_code, state)| KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key_code),
+            state,
+        })
+        .collect()
+}
+
+/// The key code of a terminal key, `None` for keys the game doesn't use
+fn key_code(code: TerminalKeyCode) -> Option<KeyCode> {
+    const FUNCTION_KEYS: [KeyCode; 12] = [
+        KeyCode::F1,
+        KeyCode::F2,
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F7,
+        KeyCode::F8,
+        KeyCode::F9,
+        KeyCode::F10,
+        KeyCode::F11,
+        KeyCode::F12,
+    ];
+    let key_code = match code {
+        TerminalKeyCode::Char(c) => return char_key_code(c.to_ascii_lowercase()),
+        TerminalKeyCode::F(n) => return FUNCTION_KEYS.get(usize::from(n).wrapping_sub(1)).copied(),
+        TerminalKeyCode::Left => KeyCode::Left,
+        TerminalKeyCode::Right => KeyCode::Right,
+        TerminalKeyCode::Up => KeyCode::Up,
+        TerminalKeyCode::Down => KeyCode::Down,
+        TerminalKeyCode::Home => KeyCode::Home,
+        TerminalKeyCode::End => KeyCode::End,
+        TerminalKeyCode::PageUp => KeyCode::PageUp,
+        TerminalKeyCode::PageDown => KeyCode::PageDown,
+        TerminalKeyCode::Insert => KeyCode::Insert,
+        TerminalKeyCode::Delete => KeyCode::Delete,
+        TerminalKeyCode::Backspace => KeyCode::Back,
+        TerminalKeyCode::Enter => KeyCode::Return,
+        TerminalKeyCode::Tab | TerminalKeyCode::BackTab => KeyCode::Tab,
+        TerminalKeyCode::Esc => KeyCode::Escape,
+        TerminalKeyCode::Null => return None,
+    };
+    Some(key_code)
+}
+
+fn char_key_code(c: char) -> Option<KeyCode> {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::A,
+        KeyCode::B,
+        KeyCode::C,
+        KeyCode::D,
+        KeyCode::E,
+        KeyCode::F,
+        KeyCode::G,
+        KeyCode::H,
+        KeyCode::I,
+        KeyCode::J,
+        KeyCode::K,
+        KeyCode::L,
+        KeyCode::M,
+        KeyCode::N,
+        KeyCode::O,
+        KeyCode::P,
+        KeyCode::Q,
+        KeyCode::R,
+        KeyCode::S,
+        KeyCode::T,
+        KeyCode::U,
+        KeyCode::V,
+        KeyCode::W,
+        KeyCode::X,
+        KeyCode::Y,
+        KeyCode::Z,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::Key0,
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ];
+    let key_code = match c {
+        'a'..='z' => LETTERS[(u32::from(c) - u32::from('a')) as usize],
+        '0'..='9' => DIGITS[(u32::from(c) - u32::from('0')) as usize],
+        ' ' => KeyCode::Space,
+        '.' => KeyCode::Period,
+        ',' => KeyCode::Comma,
+        '/' => KeyCode::Slash,
+        '-' => KeyCode::Minus,
+        '=' => KeyCode::Equals,
+        ';' => KeyCode::Semicolon,
+        '\'' => KeyCode::Apostrophe,
+        '[' => KeyCode::LBracket,
+        ']' => KeyCode::RBracket,
+        '\\' => KeyCode::Backslash,
+        '`' => KeyCode::Grave,
+        _ => return None,
+    };
+    Some(key_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_changes_are_drawn() {
+        let mut grid = Grid::new(UVec2::new(4, 4));
+        grid.cells[0].glyph = '@';
+
+        let mut out = Vec::new();
+        draw(&mut out, &grid, &Grid::default()).expect("failed to draw");
+        assert!(String::from_utf8_lossy(&out).contains('@'));
+
+        let mut out = Vec::new();
+        draw(&mut out, &grid, &grid).expect("failed to draw");
+        assert!(out.is_empty());
+
+        let mut changed = grid.clone();
+        changed.cells[5].glyph = 'g';
+        let mut out = Vec::new();
+        draw(&mut out, &changed, &grid).expect("failed to draw");
+        let out = String::from_utf8_lossy(&out);
+        assert!(out.contains('g'));
+        assert!(!out.contains('@'));
+    }
+
+    #[test]
+    fn key_codes() {
+        assert_eq!(key_code(TerminalKeyCode::Char('a')), Some(KeyCode::A));
+        assert_eq!(key_code(TerminalKeyCode::Char('Z')), Some(KeyCode::Z));
+        assert_eq!(key_code(TerminalKeyCode::Char('7')), Some(KeyCode::Key7));
+        assert_eq!(key_code(TerminalKeyCode::Char(' ')), Some(KeyCode::Space));
+        assert_eq!(key_code(TerminalKeyCode::Char('é')), None);
+        assert_eq!(key_code(TerminalKeyCode::Up), Some(KeyCode::Up));
+        assert_eq!(key_code(TerminalKeyCode::Esc), Some(KeyCode::Escape));
+        assert_eq!(key_code(TerminalKeyCode::F(1)), Some(KeyCode::F1));
+        assert_eq!(key_code(TerminalKeyCode::F(0)), None);
+        assert_eq!(key_code(TerminalKeyCode::F(13)), None);
+    }
+
+    #[test]
+    fn keys_are_released_in_the_next_frame() {
+        fn states(events: Vec<KeyboardInput>) -> Vec<(Option<KeyCode>, ElementState)> {
+            events
+                .into_iter()
+                .map(|event| (event.key_code, event.state))
+                .collect()
+        }
+        let mut held_keys = vec![];
+        assert_eq!(
+            states(key_events(&mut held_keys, vec![KeyCode::Space])),
+            vec![(Some(KeyCode::Space), ElementState::Pressed)]
+        );
+        // pressed again, the key is released first so it's just pressed again
+        assert_eq!(
+            states(key_events(&mut held_keys, vec![KeyCode::Space, KeyCode::A])),
+            vec![
+                (Some(KeyCode::Space), ElementState::Released),
+                (Some(KeyCode::Space), ElementState::Pressed),
+                (Some(KeyCode::A), ElementState::Pressed)
+            ]
+        );
+        assert_eq!(
+            states(key_events(&mut held_keys, vec![])),
+            vec![
+                (Some(KeyCode::Space), ElementState::Released),
+                (Some(KeyCode::A), ElementState::Released)
+            ]
+        );
+        assert!(key_events(&mut held_keys, vec![]).is_empty());
+    }
+}
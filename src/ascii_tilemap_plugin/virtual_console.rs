@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+
+use super::{
+    grid::{compose, Grid},
+    render::RenderLayers,
+    Layer, LayerEntities,
+};
+
+/// What the layers show after the draw commands of the last frame, nothing needs a GPU
+#[derive(Debug, Default)]
+pub struct VirtualConsole {
+    layers: Vec<Grid>,
+    screen: Grid,
+}
+
+impl VirtualConsole {
+    /// What a single layer shows, the layers below are left out and hidden layers are empty
+    #[allow(unused)]
+    #[must_use]
+    pub fn layer<I: Into<u16>>(&self, id: I) -> Option<&Grid> {
+        self.layers.get(usize::from(id.into()))
+    }
+
+    /// Every layer composited like on a screen
+    #[must_use]
+    pub fn screen(&self) -> &Grid {
+        &self.screen
+    }
+}
+
+pub fn update_virtual_console(
+    layers: Query<&Layer>,
+    layer_entities: Res<LayerEntities>,
+    render_layers: Res<RenderLayers>,
+    mut console: ResMut<VirtualConsole>,
+) {
+    // puffin::profile_function!();
+    let layers = layer_entities
+        .iter()
+        .filter_map(|entity| layers.get(*entity).ok())
+        .collect::<Vec<_>>();
+    console.layers = layers
+        .iter()
+        .map(|layer| compose(&[*layer], &render_layers))
+        .collect();
+    console.screen = compose(&layers, &render_layers);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ascii_tilemap_plugin::{
+        AsciiTilemapPlugin, Backend, DrawContext, LayerDataBuilder, TilemapBuilder, TilemapDrawing,
+    };
+
+    /// Whether the overlay is shown on the next frame
+    struct ShowOverlay(bool);
+
+    /// A map of 2 by 1 tiles under an overlay of the same size, drawn on the first frame only
+    fn app() -> App {
+        fn draw(mut ctx: DrawContext, show: Res<ShowOverlay>, mut is_drawn: Local<bool>) {
+            ctx.set_layer_visible(1, show.0);
+            if !*is_drawn {
+                *is_drawn = true;
+                ctx.set_active_layer(0);
+                ctx.print(0, 0, "##");
+                ctx.set_active_layer(1);
+                ctx.print(1, 0, "@");
+            }
+        }
+        let mut app = App::new();
+        app.insert_resource(
+            TilemapBuilder::new()
+                .with_layer(LayerDataBuilder::new(0_u16).size(2, 1).tile_size(8., 8.))
+                .with_layer(
+                    LayerDataBuilder::new(1_u16)
+                        .size(2, 1)
+                        .tile_size(8., 8.)
+                        .is_transparent(true)
+                        .is_background_transparent(true),
+                )
+                .build(),
+        )
+        .insert_resource(ShowOverlay(true))
+        .add_plugin(AsciiTilemapPlugin::new(Backend::Virtual))
+        .add_system(draw.before(TilemapDrawing));
+        app
+    }
+
+    fn console(app: &App) -> &VirtualConsole {
+        app.world
+            .get_resource::<VirtualConsole>()
+            .expect("no virtual console")
+    }
+
+    #[test]
+    fn hidden_layers_are_left_out() {
+        let mut app = app();
+        app.update();
+        assert_eq!(console(&app).screen().to_string(), "#@");
+
+        app.insert_resource(ShowOverlay(false));
+        app.update();
+        let console = console(&app);
+        assert_eq!(console.screen().to_string(), "##");
+        assert_eq!(
+            console.layer(1_u16).map(ToString::to_string),
+            Some("  ".into())
+        );
+    }
+
+    #[test]
+    fn shown_layers_keep_what_was_drawn() {
+        let mut app = app();
+        app.insert_resource(ShowOverlay(false));
+        app.update();
+        assert_eq!(console(&app).screen().to_string(), "##");
+
+        app.insert_resource(ShowOverlay(true));
+        app.update();
+        assert_eq!(console(&app).screen().to_string(), "#@");
+    }
+}
@@ -0,0 +1,170 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+
+use crate::rusty_dungeon_plugin::CornerCutting;
+
+/// Options given on the command line
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Options {
+    /// Seed of the game, a random one is used when absent
+    pub seed: Option<u64>,
+    /// Loads the saved game instead of starting a new one
+    pub continue_game: bool,
+    /// Simulates the game without a window and prints a summary
+    pub headless: bool,
+    /// Plays in the terminal instead of a window
+    pub terminal: bool,
+    /// Number of turns simulated in headless mode
+    pub turns: Option<u32>,
+    /// Prints the screen at the end of a headless run
+    pub screen: bool,
+    /// Records the actions of the player to this file
+    pub record: Option<PathBuf>,
+    /// Replays the game recorded in this file
+    pub replay: Option<PathBuf>,
+    /// Rule for diagonal steps around walls in new games
+    pub corner_cutting: Option<CornerCutting>,
+}
+
+impl Options {
+    /// Parses the arguments, without the program name
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--continue" => options.continue_game = true,
+                "--headless" => options.headless = true,
+                "--terminal" => options.terminal = true,
+                "--screen" => options.screen = true,
+                "--turns" => {
+                    let turns = args.next().context("--turns requires a value")?;
+                    options.turns = Some(
+                        turns
+                            .parse()
+                            .with_context(|| format!("invalid number of turns {:?}", turns))?,
+                    );
+                }
+                "--seed" => {
+                    let seed = args.next().context("--seed requires a value")?;
+                    options.seed = Some(
+                        seed.parse()
+                            .with_context(|| format!("invalid seed {:?}", seed))?,
+                    );
+                }
+                "--record" => {
+                    options.record = Some(args.next().context("--record requires a file")?.into());
+                }
+                "--replay" => {
+                    options.replay = Some(args.next().context("--replay requires a file")?.into());
+                }
+                "--corner-cutting" => {
+                    let rule = args
+                        .next()
+                        .context("--corner-cutting requires allow or forbid")?;
+                    options.corner_cutting = Some(rule.parse()?);
+                }
+                _ => bail!("unknown argument {:?}", arg),
+            }
+        }
+        if options.turns.is_some() && !options.headless {
+            bail!("--turns requires --headless");
+        }
+        if options.screen && !options.headless {
+            bail!("--screen requires --headless");
+        }
+        if options.headless && options.terminal {
+            bail!("--headless doesn't show the game, it can't be combined with --terminal");
+        }
+        if options.replay.is_some() && options.seed.is_some() {
+            bail!("--replay uses the seed of the recording, it can't be combined with --seed");
+        }
+        if options.replay.is_some() && options.corner_cutting.is_some() {
+            bail!("--replay uses the rules of the recording, it can't be combined with --corner-cutting");
+        }
+        if options.continue_game && (options.replay.is_some() || options.record.is_some()) {
+            bail!("a saved game can't be recorded or replayed, it doesn't start from the seed");
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options> {
+        Options::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn no_arguments() {
+        assert_eq!(parse(&[]).expect("failed to parse"), Options::default());
+    }
+
+    #[test]
+    fn seed() {
+        let options = parse(&["--seed", "1234"]).expect("failed to parse");
+        assert_eq!(options.seed, Some(1234));
+    }
+
+    #[test]
+    fn continue_game() {
+        let options = parse(&["--continue", "--seed", "7"]).expect("failed to parse");
+        assert!(options.continue_game);
+        assert_eq!(options.seed, Some(7));
+    }
+
+    #[test]
+    fn headless() {
+        let options = parse(&["--headless", "--turns", "50"]).expect("failed to parse");
+        assert!(options.headless);
+        assert_eq!(options.turns, Some(50));
+        assert!(!options.screen);
+        let options = parse(&["--headless", "--screen"]).expect("failed to parse");
+        assert!(options.screen);
+    }
+
+    #[test]
+    fn terminal() {
+        let options = parse(&["--terminal", "--seed", "3"]).expect("failed to parse");
+        assert!(options.terminal);
+        assert!(!options.headless);
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let options =
+            parse(&["--record", "new.ron", "--replay", "old.ron"]).expect("failed to parse");
+        assert_eq!(options.record, Some(PathBuf::from("new.ron")));
+        assert_eq!(options.replay, Some(PathBuf::from("old.ron")));
+    }
+
+    #[test]
+    fn corner_cutting() {
+        let options = parse(&["--corner-cutting", "allow"]).expect("failed to parse");
+        assert_eq!(options.corner_cutting, Some(CornerCutting::Allow));
+        let options = parse(&["--corner-cutting", "forbid"]).expect("failed to parse");
+        assert_eq!(options.corner_cutting, Some(CornerCutting::Forbid));
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert!(parse(&["--seed"]).is_err());
+        assert!(parse(&["--seed", "abc"]).is_err());
+        assert!(parse(&["--seed", "-1"]).is_err());
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["--turns", "5"]).is_err());
+        assert!(parse(&["--screen"]).is_err());
+        assert!(parse(&["--headless", "--turns", "many"]).is_err());
+        assert!(parse(&["--record"]).is_err());
+        assert!(parse(&["--headless", "--terminal"]).is_err());
+        assert!(parse(&["--replay", "game.ron", "--seed", "7"]).is_err());
+        assert!(parse(&["--continue", "--record", "game.ron"]).is_err());
+        assert!(parse(&["--corner-cutting"]).is_err());
+        assert!(parse(&["--corner-cutting", "sometimes"]).is_err());
+        assert!(parse(&["--replay", "game.ron", "--corner-cutting", "allow"]).is_err());
+    }
+}
@@ -1,7 +1,10 @@
 use bevy::{app::AppExit, prelude::*};
 
 use crate::ascii_tilemap_plugin::{DrawContext, TilemapDrawing};
+use crate::game_rng::GameRng;
+use crate::key_bindings::{Action, KeyBindings, KEY_BINDINGS_PATH};
 use crate::{HEIGHT, WIDTH};
+use fastrand::Rng;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 enum GameState {
@@ -14,7 +17,17 @@ pub struct FlappyPlugin;
 
 impl Plugin for FlappyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_state(GameState::Menu)
+        app.init_resource::<GameRng>();
+        let obstacle = Obstacle::new(
+            WIDTH,
+            0,
+            app.world
+                .get_resource_mut::<GameRng>()
+                .expect("GameRng not initialized")
+                .rng(),
+        );
+        app.insert_resource(KeyBindings::load_or_default(KEY_BINDINGS_PATH))
+            .add_state(GameState::Menu)
             .add_system_set(
                 SystemSet::on_update(GameState::Menu).with_system(menu.before(TilemapDrawing)),
             )
@@ -32,7 +45,7 @@ impl Plugin for FlappyPlugin {
             .insert_resource(Player::new(5, 25))
             .insert_resource(FrameTime(0.0))
             .insert_resource(Score(0))
-            .insert_resource(Obstacle::new(WIDTH, 0));
+            .insert_resource(obstacle);
     }
 }
 
@@ -60,7 +73,7 @@ impl Player {
     }
 
     fn render(&self, ctx: &mut DrawContext) {
-        ctx.set(0, self.y, Color::RED, Color::YELLOW, 1 as char);
+        ctx.set(0, self.y, Color::RED, Color::YELLOW, '☺');
     }
 
     #[allow(clippy::cast_sign_loss)]
@@ -87,10 +100,10 @@ struct Obstacle {
 }
 
 impl Obstacle {
-    fn new(x: u32, score: u32) -> Self {
+    fn new(x: u32, score: u32, rng: &mut Rng) -> Self {
         Self {
             x,
-            gap_y: fastrand::u32(10..40),
+            gap_y: rng.u32(10..40),
             size: u32::max(2, 20 - score),
         }
     }
@@ -99,7 +112,7 @@ impl Obstacle {
         let screen_x = self.x - player_x;
         let half_size = self.size / 2;
 
-        let char = 177 as char; // ASCII code 177 = ▒ ( Graphic character, medium density dotted )
+        let char = '▒';
 
         for y in 0..self.gap_y - half_size {
             ctx.set(screen_x, y, Color::BLACK, Color::GREEN, char);
@@ -130,6 +143,7 @@ fn restart(
     mut player: ResMut<Player>,
     mut obstacle: ResMut<Obstacle>,
     mut score: ResMut<Score>,
+    mut rng: ResMut<GameRng>,
 ) {
     // puffin::profile_function!();
     if events.iter().count() == 0 {
@@ -138,27 +152,27 @@ fn restart(
     info!("Restarting...");
     state.set(GameState::Playing).expect("failed to set state");
     *player = Player::new(5, 25);
-    *obstacle = Obstacle::new(WIDTH, 0);
+    *obstacle = Obstacle::new(WIDTH, 0, rng.rng());
     score.0 = 0;
 }
 
 fn menu(
     mut ctx: DrawContext,
     keyboard_input: Res<Input<KeyCode>>,
-    mut app_exit_events: EventWriter<AppExit>,
-    mut restart_events: EventWriter<RestartEvent>,
+    bindings: Res<KeyBindings>,
+    app_exit_events: EventWriter<AppExit>,
+    restart_events: EventWriter<RestartEvent>,
 ) {
     // puffin::profile_function!();
     ctx.cls();
     ctx.print_centered(5, "Welcome to Flappy Dragon");
-    ctx.print_centered(8, "(P) Play Game");
-    ctx.print_centered(9, "(Q) Quit Game");
-
-    if keyboard_input.just_pressed(KeyCode::P) {
-        restart_events.send(RestartEvent);
-    } else if keyboard_input.just_pressed(KeyCode::Q) {
-        app_exit_events.send(AppExit);
-    }
+    handle_menu_input(
+        &mut ctx,
+        &keyboard_input,
+        &bindings,
+        app_exit_events,
+        restart_events,
+    );
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -171,6 +185,8 @@ fn play(
     mut obstacle: ResMut<Obstacle>,
     mut score: ResMut<Score>,
     keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut rng: ResMut<GameRng>,
 ) {
     // puffin::profile_function!();
     ctx.cls();
@@ -180,18 +196,22 @@ fn play(
         player.gravity_and_move();
     }
 
-    if keyboard_input.pressed(KeyCode::Space) {
+    if bindings.pressed(&keyboard_input, Action::Flap) {
         player.flap();
     }
 
     player.render(&mut ctx);
-    ctx.print(0, 0, "Press SPACE to flap.");
+    ctx.print(
+        0,
+        0,
+        &format!("Press {} to flap.", bindings.key_name(Action::Flap)),
+    );
     ctx.print(0, 1, &format!("Score: {}", score.0));
 
     obstacle.render(&mut ctx, player.x);
     if player.x > obstacle.x {
         score.0 += 1;
-        *obstacle = Obstacle::new(player.x + WIDTH, score.0);
+        *obstacle = Obstacle::new(player.x + WIDTH, score.0, rng.rng());
     }
 
     if player.y > HEIGHT || obstacle.hit(&player) {
@@ -202,20 +222,43 @@ fn play(
 fn end(
     mut ctx: DrawContext,
     keyboard_input: Res<Input<KeyCode>>,
-    mut app_exit_events: EventWriter<AppExit>,
-    mut restart_events: EventWriter<RestartEvent>,
+    bindings: Res<KeyBindings>,
+    app_exit_events: EventWriter<AppExit>,
+    restart_events: EventWriter<RestartEvent>,
     score: Res<Score>,
 ) {
     // puffin::profile_function!();
     ctx.cls();
     ctx.print_centered(5, "You are dead");
     ctx.print_centered(6, &format!("You earned {} points", score.0));
-    ctx.print_centered(8, "(P) Play Game");
-    ctx.print_centered(9, "(Q) Quit Game");
+    handle_menu_input(
+        &mut ctx,
+        &keyboard_input,
+        &bindings,
+        app_exit_events,
+        restart_events,
+    );
+}
+
+fn handle_menu_input(
+    ctx: &mut DrawContext,
+    keyboard_input: &Input<KeyCode>,
+    bindings: &KeyBindings,
+    mut app_exit_events: EventWriter<AppExit>,
+    mut restart_events: EventWriter<RestartEvent>,
+) {
+    ctx.print_centered(
+        8,
+        &format!("({}) Play Game", bindings.key_name(Action::Restart)),
+    );
+    ctx.print_centered(
+        9,
+        &format!("({}) Quit Game", bindings.key_name(Action::Quit)),
+    );
 
-    if keyboard_input.just_pressed(KeyCode::P) {
+    if bindings.just_pressed(keyboard_input, Action::Restart) {
         restart_events.send(RestartEvent);
-    } else if keyboard_input.just_pressed(KeyCode::Q) {
+    } else if bindings.just_pressed(keyboard_input, Action::Quit) {
         app_exit_events.send(AppExit);
     }
 }
@@ -0,0 +1,132 @@
+use std::sync::{Mutex, PoisonError};
+
+use fastrand::Rng;
+
+/// The random number generator shared by every system.
+/// Everything random in a game only depends on the seed, so a run can be reproduced.
+///
+/// The state of a `fastrand::Rng` can't be read without drawing from it, so every call to
+/// `rng` starts a new sequence derived from a mix of the seed and the number of calls.
+/// That number is the whole state, reading it doesn't change the numbers drawn.
+#[derive(Debug)]
+pub struct GameRng {
+    seed: u64,
+    /// Calls to `rng` so far
+    uses: u64,
+    // fastrand::Rng is not Sync, the mutex makes it usable as a resource
+    rng: Mutex<Rng>,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(fastrand::u64(..))
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self::from_state(seed, 0)
+    }
+
+    /// Continues the game of the given seed from a state returned by `state`
+    pub fn from_state(seed: u64, state: u64) -> Self {
+        Self {
+            seed,
+            uses: state,
+            rng: Mutex::new(Rng::new()),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// A state that can be saved,
+    /// a generator created with `from_state` then produces the same numbers as this one
+    pub fn state(&self) -> u64 {
+        self.uses
+    }
+
+    pub fn rng(&mut self) -> &mut Rng {
+        self.uses += 1;
+        // mixed twice so a seed doesn't replay the sequences of the next seed one call later
+        let seed = splitmix64(splitmix64(self.seed) ^ self.uses);
+        let rng = self.rng.get_mut().unwrap_or_else(PoisonError::into_inner);
+        rng.seed(seed);
+        rng
+    }
+}
+
+/// A step of splitmix64, close inputs give unrelated outputs
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut a = GameRng::new(42);
+        let mut b = GameRng::new(42);
+        let a = (0..10).map(|_| a.rng().u32(..)).collect::<Vec<_>>();
+        let b = (0..10).map(|_| b.rng().u32(..)).collect::<Vec<_>>();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn from_state_same_numbers() {
+        let mut a = GameRng::new(42);
+        a.rng().u32(..);
+        let mut b = GameRng::from_state(42, a.state());
+        assert_eq!(b.seed(), 42);
+        let a = (0..10).map(|_| a.rng().u32(..)).collect::<Vec<_>>();
+        let b = (0..10).map(|_| b.rng().u32(..)).collect::<Vec<_>>();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn reading_the_state_keeps_the_numbers() {
+        let mut a = GameRng::new(42);
+        let mut b = GameRng::new(42);
+        a.rng().u32(..);
+        b.rng().u32(..);
+        assert_eq!(a.state(), b.state());
+        let a = (0..10).map(|_| a.rng().u32(..)).collect::<Vec<_>>();
+        let b = (0..10).map(|_| b.rng().u32(..)).collect::<Vec<_>>();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn every_call_draws_new_numbers() {
+        let mut rng = GameRng::new(42);
+        let first = rng.rng().u64(..);
+        assert_ne!(rng.rng().u64(..), first);
+        // a sequence continues until the next call
+        let rng = rng.rng();
+        assert_ne!(rng.u64(..), rng.u64(..));
+    }
+
+    #[test]
+    fn different_seed_different_numbers() {
+        let mut a = GameRng::new(1);
+        let mut b = GameRng::new(2);
+        let a = (0..10).map(|_| a.rng().u32(..)).collect::<Vec<_>>();
+        let b = (0..10).map(|_| b.rng().u32(..)).collect::<Vec<_>>();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn next_seed_different_numbers() {
+        let mut a = GameRng::new(42);
+        let mut b = GameRng::new(43);
+        let a = (0..100).map(|_| a.rng().u64(..)).collect::<Vec<_>>();
+        let b = (0..100).map(|_| b.rng().u64(..)).collect::<Vec<_>>();
+        // the sequences of a seed aren't the ones of the next seed shifted by some calls
+        assert!(a.iter().all(|number| !b.contains(number)));
+    }
+}
@@ -0,0 +1,305 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// Where the key bindings are loaded from, relative to the working directory
+pub const KEY_BINDINGS_PATH: &str = "assets/keybindings.ron";
+/// Used when the key bindings can't be loaded
+const DEFAULT_KEY_BINDINGS: &str = include_str!("../assets/keybindings.ron");
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        [$((stringify!($key), KeyCode::$key)),*]
+    };
+}
+
+/// Keys that can be bound, named like their `KeyCode`
+#[rustfmt::skip]
+const KEY_NAMES: &[(&str, KeyCode)] = &key_names![
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide, NumpadDecimal, NumpadEnter,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Left, Right, Up, Down, Home, End, PageUp, PageDown, Insert, Delete,
+    Escape, Tab, Space, Return, Back,
+    Apostrophe, Backslash, Comma, Equals, Grave, LBracket, RBracket, Minus, Period, Semicolon,
+    Slash, LShift, RShift, LControl, RControl, LAlt, RAlt,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// The step taken in this direction, y grows downward
+    pub fn delta(self) -> IVec2 {
+        match self {
+            Direction::North => IVec2::new(0, -1),
+            Direction::NorthEast => IVec2::new(1, -1),
+            Direction::East => IVec2::new(1, 0),
+            Direction::SouthEast => IVec2::new(1, 1),
+            Direction::South => IVec2::new(0, 1),
+            Direction::SouthWest => IVec2::new(-1, 1),
+            Direction::West => IVec2::new(-1, 0),
+            Direction::NorthWest => IVec2::new(-1, -1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Action {
+    Move(Direction),
+    Wait,
+    PickUp,
+    /// Uses the item at this index of the inventory
+    UseItem(usize),
+    Descend,
+    Save,
+    /// Shows or hides the map of the whole level
+    Minimap,
+    Restart,
+    Quit,
+    Flap,
+}
+
+impl Action {
+    /// Actions taking a turn in the dungeon, a key can only be bound to one of them
+    pub fn is_turn(self) -> bool {
+        matches!(
+            self,
+            Action::Move(_) | Action::Wait | Action::PickUp | Action::UseItem(_) | Action::Descend
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Binding {
+    action: Action,
+    keys: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Config {
+    bindings: Vec<Binding>,
+}
+
+/// The keys of every action, shared by both games
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    bindings: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::from_ron(DEFAULT_KEY_BINDINGS)
+            .unwrap_or_else(|e| panic!("invalid default key bindings: {:?}", e))
+    }
+}
+
+impl KeyBindings {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read key bindings from {}", path.display()))?;
+        Self::from_ron(&text)
+            .with_context(|| format!("failed to load key bindings from {}", path.display()))
+    }
+
+    /// Falls back to the default bindings so a broken file doesn't prevent playing
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Self {
+        Self::load(path).unwrap_or_else(|e| {
+            warn!("Using the default key bindings: {:?}", e);
+            Self::default()
+        })
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self> {
+        let config: Config = ron::from_str(text).context("failed to parse key bindings")?;
+        let mut bindings = Vec::<(Action, Vec<KeyCode>)>::new();
+        for binding in config.bindings {
+            let keys = binding
+                .keys
+                .iter()
+                .map(|name| {
+                    key_code(name)
+                        .with_context(|| format!("unknown key {:?} for {:?}", name, binding.action))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if binding.action.is_turn() {
+                let other = bindings.iter().find(|(action, other_keys)| {
+                    action.is_turn()
+                        && *action != binding.action
+                        && other_keys.iter().any(|key| keys.contains(key))
+                });
+                if let Some((other, _)) = other {
+                    bail!(
+                        "a key is bound to both {:?} and {:?}",
+                        other,
+                        binding.action
+                    );
+                }
+            }
+            match bindings
+                .iter_mut()
+                .find(|(action, _)| *action == binding.action)
+            {
+                Some((_, other_keys)) => other_keys.extend(keys),
+                None => bindings.push((binding.action, keys)),
+            }
+        }
+        Ok(Self { bindings })
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings
+            .iter()
+            .find(|(other, _)| *other == action)
+            .map_or(&[], |(_, keys)| keys.as_slice())
+    }
+
+    /// The dungeon action of a key
+    pub fn turn_action(&self, key_code: KeyCode) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(action, keys)| action.is_turn() && keys.contains(&key_code))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn pressed(&self, input: &Input<KeyCode>, action: Action) -> bool {
+        self.keys(action).iter().any(|key| input.pressed(*key))
+    }
+
+    pub fn just_pressed(&self, input: &Input<KeyCode>, action: Action) -> bool {
+        self.keys(action).iter().any(|key| input.just_pressed(*key))
+    }
+
+    /// Name of the first key of an action, to show it to the player
+    pub fn key_name(&self, action: Action) -> &'static str {
+        self.keys(action)
+            .first()
+            .and_then(|key| key_name(*key))
+            .unwrap_or("?")
+    }
+
+    /// Names of the first key of each action, like "WASD" or "Up/Left/Down/Right"
+    pub fn keys_hint(&self, actions: &[Action]) -> String {
+        let names = actions
+            .iter()
+            .map(|action| self.key_name(*action))
+            .collect::<Vec<_>>();
+        if names.iter().all(|name| name.chars().count() == 1) {
+            names.concat()
+        } else {
+            names.join("/")
+        }
+    }
+}
+
+fn key_code(name: &str) -> Option<KeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| *key_name == name)
+        .map(|(_, key)| *key)
+}
+
+fn key_name(key_code: KeyCode) -> Option<&'static str> {
+    KEY_NAMES
+        .iter()
+        .find(|(_, key)| *key == key_code)
+        .map(|(name, _)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings() {
+        let bindings = KeyBindings::default();
+        assert_eq!(
+            KeyBindings::load(KEY_BINDINGS_PATH).expect("failed to load the key bindings"),
+            bindings
+        );
+        let north = Some(Action::Move(Direction::North));
+        assert_eq!(bindings.turn_action(KeyCode::Up), north);
+        assert_eq!(bindings.turn_action(KeyCode::W), north);
+        assert_eq!(bindings.turn_action(KeyCode::K), north);
+        assert_eq!(
+            bindings.turn_action(KeyCode::Y),
+            Some(Action::Move(Direction::NorthWest))
+        );
+        assert_eq!(
+            bindings.turn_action(KeyCode::Key3),
+            Some(Action::UseItem(2))
+        );
+        // quitting doesn't take a turn
+        assert_eq!(bindings.turn_action(KeyCode::Q), None);
+        assert_eq!(bindings.keys(Action::Quit), &[KeyCode::Q]);
+        assert_eq!(bindings.key_name(Action::Save), "F5");
+        assert_eq!(bindings.key_name(Action::Minimap), "M");
+        assert_eq!(bindings.keys_hint(&[Action::PickUp, Action::Quit]), "GQ");
+    }
+
+    #[test]
+    fn azerty() {
+        let text = r#"(
+            bindings: [
+                (action: Move(North), keys: ["Z", "Up"]),
+                (action: Move(West), keys: ["Q"]),
+                (action: Quit, keys: ["Escape"]),
+            ],
+        )"#;
+        let bindings = KeyBindings::from_ron(text).expect("failed to parse");
+        assert_eq!(
+            bindings.turn_action(KeyCode::Q),
+            Some(Action::Move(Direction::West))
+        );
+        assert_eq!(bindings.turn_action(KeyCode::W), None);
+        assert_eq!(bindings.key_name(Action::Quit), "Escape");
+        assert_eq!(bindings.key_name(Action::Flap), "?");
+        assert_eq!(
+            bindings.keys_hint(&[Action::Move(Direction::North), Action::Quit]),
+            "Z/Escape"
+        );
+    }
+
+    #[test]
+    fn invalid_bindings() {
+        let invalid = [
+            r#"(bindings: [(action: Wait, keys: ["Spacebar"])])"#,
+            r#"(bindings: [(action: Jump, keys: ["Space"])])"#,
+            r#"(bindings: [(action: Wait, keys: ["Space"]), (action: PickUp, keys: ["Space"])])"#,
+        ];
+        for text in invalid {
+            assert!(KeyBindings::from_ron(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn key_can_take_a_turn_and_flap() {
+        let text =
+            r#"(bindings: [(action: Wait, keys: ["Space"]), (action: Flap, keys: ["Space"])])"#;
+        let bindings = KeyBindings::from_ron(text).expect("failed to parse");
+        assert_eq!(bindings.turn_action(KeyCode::Space), Some(Action::Wait));
+        assert_eq!(bindings.keys(Action::Flap), &[KeyCode::Space]);
+    }
+
+    #[test]
+    fn key_names_round_trip() {
+        for &(name, key) in KEY_NAMES {
+            assert_eq!(key_code(name), Some(key));
+            assert_eq!(key_name(key), Some(name));
+        }
+    }
+}
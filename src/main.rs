@@ -1,3 +1,4 @@
+#![cfg_attr(test, feature(test))]
 #![warn(clippy::pedantic)]
 #![allow(
     clippy::needless_pass_by_value,
@@ -6,12 +7,29 @@
 )]
 
 mod ascii_tilemap_plugin;
+mod cli;
 mod flappy_plugin;
+mod game_rng;
+mod key_bindings;
 mod rusty_dungeon_plugin;
 // mod profiler_plugin;
 
-use crate::ascii_tilemap_plugin::{AsciiTilemapPlugin, LayerDataBuilder, TilemapBuilder};
-use bevy::{diagnostic::FrameTimeDiagnosticsPlugin, prelude::*};
+use crate::ascii_tilemap_plugin::{AsciiTilemapPlugin, Backend, RenderStats, VirtualConsole};
+use crate::cli::Options;
+use crate::game_rng::GameRng;
+use crate::rusty_dungeon_plugin::{
+    headless::{add_virtual_console, headless_app, run_headless, ScriptedInput},
+    replay::{Playback, Recorder, Recording},
+    tilemap_builder,
+};
+use bevy::{
+    app::ScheduleRunnerSettings,
+    diagnostic::{DiagnosticsPlugin, FrameTimeDiagnosticsPlugin},
+    input::InputPlugin,
+    prelude::*,
+    window::WindowPlugin,
+};
+use std::time::Duration;
 // use bevy_egui::EguiPlugin;
 
 pub const WIDTH: u32 = 80;
@@ -28,11 +46,19 @@ pub const WINDOW_WIDTH: f32 = DISPLAY_WIDTH as f32 * TILE_WIDTH as f32;
 #[allow(clippy::cast_precision_loss)]
 pub const WINDOW_HEIGHT: f32 = DISPLAY_HEIGHT as f32 * TILE_HEIGHT as f32;
 
+/// Turns simulated by `--headless` when `--turns` is not given
+const DEFAULT_HEADLESS_TURNS: u32 = 100;
+/// Frames between two turns of a `--replay`, so it can be followed
+const REPLAY_FRAMES_PER_TURN: u32 = 6;
+/// Frames per second of `--terminal`, there is no vsync to limit them
+const TERMINAL_FPS: f64 = 30.;
+
 pub enum LayerId {
     Map = 0,
     Entities = 1,
     Hud = 2,
     Diagnostic = 3,
+    Minimap = 4,
 }
 
 impl From<LayerId> for u16 {
@@ -42,59 +68,97 @@ impl From<LayerId> for u16 {
 }
 
 fn main() {
-    App::new()
-        .insert_resource(WindowDescriptor {
-            // TODO find a way to control this by the plugin
-            // if they don't match the map will not be aligned properly
-            // or update the tilemap size on resize
-            width: WINDOW_WIDTH,
-            height: WINDOW_HEIGHT,
-            title: String::from("hands on dungeon crawler"),
-            // vsync: false,
-            ..Default::default()
-        })
-        // .insert_resource(ClearColor(Color::PINK))
-        .add_plugins(DefaultPlugins)
+    let options = Options::parse(std::env::args().skip(1))
+        .unwrap_or_else(|e| panic!("invalid command line arguments: {:?}", e));
+    let recording = options.replay.as_ref().map(|path| {
+        Recording::load(path).unwrap_or_else(|e| panic!("failed to load the replay: {:?}", e))
+    });
+    let rng = recording
+        .as_ref()
+        .map(|recording| recording.seed)
+        .or(options.seed)
+        .map_or_else(GameRng::default, GameRng::new);
+    let corner_cutting = recording
+        .as_ref()
+        .map(|recording| recording.corner_cutting)
+        .or(options.corner_cutting)
+        .unwrap_or_default();
+    let recorder = options
+        .record
+        .as_ref()
+        .map_or_else(Recorder::default, Recorder::to_file);
+
+    if options.headless {
+        // a replay plays the recorded turns, otherwise random keys are pressed
+        let (input, turns, playback) = match recording {
+            Some(recording) => (
+                ScriptedInput::default(),
+                u32::try_from(recording.actions.len()).unwrap_or(u32::MAX),
+                Playback::new(recording.actions, 0),
+            ),
+            None => (
+                ScriptedInput::random(rng.seed()),
+                DEFAULT_HEADLESS_TURNS,
+                Playback::default(),
+            ),
+        };
+        let mut app = headless_app(rng, input);
+        app.insert_resource(playback)
+            .insert_resource(recorder)
+            .insert_resource(corner_cutting);
+        if options.screen {
+            add_virtual_console(&mut app);
+        }
+        println!("{}", run_headless(&mut app, options.turns.unwrap_or(turns)));
+        if let Some(console) = app.world.get_resource::<VirtualConsole>() {
+            println!("{}", console.screen());
+        }
+        if let Some(stats) = app.world.get_resource::<RenderStats>() {
+            println!("{}", stats);
+        }
+        return;
+    }
+    let playback = recording.map_or_else(Playback::default, |recording| {
+        Playback::new(recording.actions, REPLAY_FRAMES_PER_TURN)
+    });
+
+    let mut app = App::new();
+    app.insert_resource(WindowDescriptor {
+        // initial size, the layers follow the window when it's resized
+        width: WINDOW_WIDTH,
+        height: WINDOW_HEIGHT,
+        title: String::from("hands on dungeon crawler"),
+        // vsync: false,
+        ..Default::default()
+    });
+    let backend = if options.terminal {
+        // no window is opened, the window plugin only provides the resources the game reads
+        app.insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
+            1. / TERMINAL_FPS,
+        )))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(WindowPlugin::default())
+        .add_plugin(InputPlugin)
+        .add_plugin(DiagnosticsPlugin)
+        // every redrawn frame is composited again
+        .insert_resource(rusty_dungeon_plugin::RedrawPolicy::OnChange);
+        Backend::Terminal
+    } else {
+        app.add_plugins(DefaultPlugins);
+        Backend::Tilemap
+    };
+    // .insert_resource(ClearColor(Color::PINK))
+    app.insert_resource(rng)
+        .insert_resource(corner_cutting)
+        .insert_resource(rusty_dungeon_plugin::ContinueGame(options.continue_game))
+        .insert_resource(recorder)
+        .insert_resource(playback)
         // .add_plugin(EguiPlugin)
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
         // .add_plugin(profiler_plugin::ProfilerPlugin)
         .add_system(bevy::input::system::exit_on_esc_system)
-        .add_plugin(AsciiTilemapPlugin)
-        .insert_resource(
-            #[allow(clippy::cast_precision_loss)]
-            TilemapBuilder::new()
-                .with_layer(
-                    LayerDataBuilder::new(LayerId::Map)
-                        .texture_path("dungeonfont.png")
-                        .size(DISPLAY_WIDTH, DISPLAY_HEIGHT)
-                        .tile_size(TILE_WIDTH as f32, TILE_HEIGHT as f32),
-                )
-                .with_layer(
-                    LayerDataBuilder::new(LayerId::Entities)
-                        .texture_path("dungeonfont.png")
-                        .size(DISPLAY_WIDTH, DISPLAY_HEIGHT)
-                        .tile_size(TILE_WIDTH as f32, TILE_HEIGHT as f32)
-                        .is_transparent(true)
-                        .is_background_transparent(true),
-                )
-                .with_layer(
-                    LayerDataBuilder::new(LayerId::Hud)
-                        .texture_path("16x16-sb-ascii.png")
-                        .size(DISPLAY_WIDTH * 2, DISPLAY_HEIGHT * 2)
-                        .tile_size(16., 16.)
-                        .is_transparent(true)
-                        .is_background_transparent(false),
-                )
-                .with_layer(
-                    LayerDataBuilder::new(LayerId::Diagnostic)
-                        .texture_path("16x16-sb-ascii.png")
-                        .size(DISPLAY_WIDTH * 2, DISPLAY_HEIGHT * 2)
-                        .tile_size(16., 16.)
-                        .is_transparent(true)
-                        .is_background_transparent(true),
-                )
-                .build(),
-        )
+        .add_plugin(AsciiTilemapPlugin::new(backend))
+        .insert_resource(tilemap_builder())
         // .add_plugin(flappy_plugin::FlappyPlugin)
         .add_plugin(rusty_dungeon_plugin::RustyDungeonPlugin)
         .run();
@@ -0,0 +1,405 @@
+use std::collections::VecDeque;
+
+use bevy::{input::keyboard::KeyboardInput, prelude::*, utils::HashSet};
+
+use super::{
+    camera::Camera,
+    components::{Enemy, FieldOfView, Name, Player, Position},
+    game_log::GameLog,
+    map::Map,
+    pathfinding::a_star,
+    replay::{Playback, PlayerAction},
+    CursorPos,
+};
+
+/// A walk over several turns toward a tile clicked by the player
+#[derive(Debug, Default)]
+pub struct AutoTravel {
+    path: VecDeque<UVec2>,
+    /// Enemies in view at the last step, another one coming into view stops the travel
+    seen_enemies: HashSet<Entity>,
+}
+
+impl AutoTravel {
+    pub fn start<I: IntoIterator<Item = Entity>>(&mut self, path: Vec<UVec2>, seen_enemies: I) {
+        self.path = path.into();
+        self.seen_enemies = seen_enemies.into_iter().collect();
+    }
+
+    pub fn cancel(&mut self) {
+        self.path.clear();
+        self.seen_enemies.clear();
+    }
+
+    pub fn is_travelling(&self) -> bool {
+        !self.path.is_empty()
+    }
+
+    /// The move toward the next tile of the path, the travel is cancelled
+    /// when the player isn't next to it anymore
+    fn next_step(&mut self, map: &Map, player: Position) -> Option<PlayerAction> {
+        let next = self.path.pop_front()?;
+        let delta = next.as_ivec2() - player.0.as_ivec2();
+        let is_adjacent = delta != IVec2::ZERO && delta.abs().max_element() == 1;
+        if !is_adjacent || !map.can_enter_tile_from(player, Position(next)) {
+            self.cancel();
+            return None;
+        }
+        Some(PlayerAction::Move {
+            dx: delta.x,
+            dy: delta.y,
+        })
+    }
+}
+
+/// Clicking a visible or revealed floor tile travels there over the tiles the player knows,
+/// clicking an adjacent enemy attacks it
+#[allow(clippy::too_many_arguments)]
+pub fn mouse_click(
+    playback: Res<Playback>,
+    mouse_input: Res<Input<MouseButton>>,
+    cursor_pos: Res<CursorPos>,
+    camera: Res<Camera>,
+    map: Res<Map>,
+    player_query: Query<(&Position, &FieldOfView), With<Player>>,
+    enemy_query: Query<(Entity, &Position), With<Enemy>>,
+    mut travel: ResMut<AutoTravel>,
+    mut log: ResMut<GameLog>,
+    mut actions: EventWriter<PlayerAction>,
+) {
+    // puffin::profile_function!();
+    if !playback.is_finished() || !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let (cursor_position, (player, fov)) = match (cursor_pos.map, player_query.get_single()) {
+        (Some(cursor_position), Ok(player)) => (cursor_position, player),
+        _ => return,
+    };
+    let offset = IVec2::new(camera.left_x, camera.top_y);
+    let map_pos = cursor_position.as_ivec2() + offset;
+    if map_pos.min_element() < 0 {
+        return;
+    }
+    let target = map_pos.as_uvec2();
+    let is_known =
+        |point: UVec2| fov.visible_tiles.contains(&point) || map.is_revealed(Position(point));
+    if !is_known(target) {
+        return;
+    }
+    travel.cancel();
+
+    // the enemies out of view are unknown, the travel stops when they're in the way
+    let is_enemy = fov.visible_tiles.contains(&target)
+        && enemy_query.iter().any(|(_, position)| position.0 == target);
+    if is_enemy {
+        let delta = map_pos - player.0.as_ivec2();
+        if delta.abs().max_element() == 1 {
+            actions.send(PlayerAction::Move {
+                dx: delta.x,
+                dy: delta.y,
+            });
+        }
+        return;
+    }
+    if !map.can_enter_tile(Position(target)) {
+        return;
+    }
+    match a_star(&map, player.0, target, is_known) {
+        Some(path) => {
+            let seen_enemies = enemy_query
+                .iter()
+                .filter(|(_, position)| fov.visible_tiles.contains(&position.0))
+                .map(|(enemy, _)| enemy);
+            travel.start(path, seen_enemies);
+        }
+        None => log.add(Color::GRAY, "You can't find a way there."),
+    }
+}
+
+/// Takes the next step of the travel, any key cancels it
+#[allow(clippy::too_many_arguments)]
+pub fn auto_travel(
+    mut travel: ResMut<AutoTravel>,
+    playback: Res<Playback>,
+    map: Res<Map>,
+    mut keyboard_input_events: EventReader<KeyboardInput>,
+    player_query: Query<(&Position, &FieldOfView), With<Player>>,
+    enemy_query: Query<(Entity, &Position, &Name), With<Enemy>>,
+    mut log: ResMut<GameLog>,
+    mut actions: EventWriter<PlayerAction>,
+) {
+    // puffin::profile_function!();
+    // read every frame, a key pressed before the travel started doesn't cancel it
+    let is_key_pressed = keyboard_input_events
+        .iter()
+        .any(|event| event.state.is_pressed());
+    if !travel.is_travelling() {
+        return;
+    }
+    if !playback.is_finished() || is_key_pressed {
+        travel.cancel();
+        return;
+    }
+    let (player, fov) = match player_query.get_single() {
+        Ok(player) => player,
+        Err(_) => {
+            travel.cancel();
+            return;
+        }
+    };
+
+    let visible_enemies = enemy_query
+        .iter()
+        .filter(|(_, position, _)| fov.visible_tiles.contains(&position.0))
+        .collect::<Vec<_>>();
+    if let Some((_, _, name)) = visible_enemies
+        .iter()
+        .find(|(enemy, _, _)| !travel.seen_enemies.contains(enemy))
+    {
+        log.add(Color::ORANGE, format!("You spot a {} and stop.", name.0));
+        travel.cancel();
+        return;
+    }
+    travel.seen_enemies = visible_enemies.iter().map(|(enemy, _, _)| *enemy).collect();
+
+    // an enemy that walked onto the path isn't attacked by surprise
+    let is_blocked = travel.path.front().is_some_and(|next| {
+        enemy_query
+            .iter()
+            .any(|(_, position, _)| position.0 == *next)
+    });
+    if is_blocked {
+        log.add(Color::GRAY, "Something blocks the way.");
+        travel.cancel();
+        return;
+    }
+    if let Some(action) = travel.next_step(&map, *player) {
+        actions.send(action);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_dungeon_plugin::map::TileType;
+    use bevy::{app::Events, input::ElementState};
+
+    fn test_world() -> World {
+        let mut world = World::new();
+        let mut map = Map::new(10, 10);
+        map.fill(TileType::Floor);
+        world.insert_resource(map);
+        world.insert_resource(AutoTravel::default());
+        world.insert_resource(Playback::default());
+        world.insert_resource(GameLog::default());
+        world.insert_resource(Events::<KeyboardInput>::default());
+        world.insert_resource(Events::<PlayerAction>::default());
+        let mut fov = FieldOfView::new(8);
+        fov.visible_tiles = (0..10)
+            .flat_map(|y| (0..10).map(move |x| UVec2::new(x, y)))
+            .collect();
+        world
+            .spawn()
+            .insert(Player)
+            .insert(Position(UVec2::new(1, 1)))
+            .insert(fov);
+        world
+    }
+
+    fn spawn_enemy(world: &mut World, position: UVec2) -> Entity {
+        world
+            .spawn()
+            .insert(Enemy)
+            .insert(Position(position))
+            .insert(Name("Goblin".to_string()))
+            .id()
+    }
+
+    fn run_step(world: &mut World) -> Vec<PlayerAction> {
+        let mut stage = SystemStage::parallel().with_system(auto_travel);
+        run_stage(&mut stage, world)
+    }
+
+    /// Runs the same system again, its event reader remembers the events already read
+    fn run_stage(stage: &mut SystemStage, world: &mut World) -> Vec<PlayerAction> {
+        stage.run(world);
+        world
+            .get_resource_mut::<Events<PlayerAction>>()
+            .expect("no events")
+            .drain()
+            .collect()
+    }
+
+    fn start_travel(world: &mut World, path: Vec<UVec2>, seen_enemies: Vec<Entity>) {
+        world
+            .get_resource_mut::<AutoTravel>()
+            .expect("no travel")
+            .start(path, seen_enemies);
+    }
+
+    fn is_travelling(world: &World) -> bool {
+        world
+            .get_resource::<AutoTravel>()
+            .is_some_and(AutoTravel::is_travelling)
+    }
+
+    #[test]
+    fn next_step() {
+        let mut map = Map::new(10, 10);
+        map.fill(TileType::Floor);
+        let mut travel = AutoTravel::default();
+        travel.start(vec![UVec2::new(2, 2), UVec2::new(3, 2)], []);
+        assert_eq!(
+            travel.next_step(&map, Position(UVec2::new(1, 1))),
+            Some(PlayerAction::Move { dx: 1, dy: 1 })
+        );
+        // the player didn't move, the rest of the path can't be followed
+        assert_eq!(travel.next_step(&map, Position(UVec2::new(1, 1))), None);
+        assert!(!travel.is_travelling());
+    }
+
+    #[test]
+    fn follows_the_path() {
+        let mut world = test_world();
+        start_travel(&mut world, vec![UVec2::new(2, 1), UVec2::new(3, 2)], vec![]);
+        assert_eq!(
+            run_step(&mut world),
+            vec![PlayerAction::Move { dx: 1, dy: 0 }]
+        );
+        assert!(is_travelling(&world));
+    }
+
+    fn press_key(world: &mut World) {
+        world
+            .get_resource_mut::<Events<KeyboardInput>>()
+            .expect("no events")
+            .send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(KeyCode::Escape),
+                state: ElementState::Pressed,
+            });
+    }
+
+    #[test]
+    fn a_key_cancels_the_travel() {
+        let mut world = test_world();
+        start_travel(&mut world, vec![UVec2::new(2, 1)], vec![]);
+        press_key(&mut world);
+        assert!(run_step(&mut world).is_empty());
+        assert!(!is_travelling(&world));
+    }
+
+    #[test]
+    fn a_key_pressed_before_the_travel_is_ignored() {
+        let mut world = test_world();
+        let mut stage = SystemStage::parallel().with_system(auto_travel);
+        press_key(&mut world);
+        assert!(run_stage(&mut stage, &mut world).is_empty());
+
+        start_travel(&mut world, vec![UVec2::new(2, 1)], vec![]);
+        assert_eq!(run_stage(&mut stage, &mut world).len(), 1);
+    }
+
+    #[test]
+    fn an_enemy_in_view_stops_the_travel() {
+        let mut world = test_world();
+        let known = spawn_enemy(&mut world, UVec2::new(8, 8));
+        start_travel(
+            &mut world,
+            vec![UVec2::new(2, 1), UVec2::new(3, 1)],
+            vec![known],
+        );
+        assert_eq!(run_step(&mut world).len(), 1);
+
+        spawn_enemy(&mut world, UVec2::new(5, 5));
+        assert!(run_step(&mut world).is_empty());
+        assert!(!is_travelling(&world));
+    }
+
+    /// Clicks a tile of the map, the camera shows the whole map
+    fn click(world: &mut World, tile: UVec2) -> Vec<PlayerAction> {
+        let mut mouse_input = Input::default();
+        mouse_input.press(MouseButton::Left);
+        world.insert_resource(mouse_input);
+        world.insert_resource(CursorPos {
+            map: Some(tile),
+            hud: None,
+        });
+        world.insert_resource(Camera::new(IVec2::new(5, 5), 10, 10));
+        let mut stage = SystemStage::parallel().with_system(mouse_click);
+        run_stage(&mut stage, world)
+    }
+
+    /// The player only sees the first columns of the map
+    fn see_columns(world: &mut World, width: u32) {
+        let mut fov = world
+            .query_filtered::<&mut FieldOfView, With<Player>>()
+            .iter_mut(world)
+            .next()
+            .expect("no player");
+        fov.visible_tiles = (0..10)
+            .flat_map(|y| (0..width).map(move |x| UVec2::new(x, y)))
+            .collect();
+    }
+
+    fn reveal(world: &mut World, tiles: &[UVec2]) {
+        let mut map = world.get_resource_mut::<Map>().expect("no map");
+        for tile in tiles {
+            map.reveal(Position(*tile));
+        }
+    }
+
+    fn travel_path(world: &World) -> Vec<UVec2> {
+        world
+            .get_resource::<AutoTravel>()
+            .map(|travel| travel.path.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn clicking_a_tile_starts_the_travel() {
+        let mut world = test_world();
+        assert!(click(&mut world, UVec2::new(4, 1)).is_empty());
+        let path = travel_path(&world);
+        assert_eq!(path.len(), 3);
+        assert_eq!(path.last(), Some(&UVec2::new(4, 1)));
+    }
+
+    #[test]
+    fn clicking_an_adjacent_enemy_attacks_it() {
+        let mut world = test_world();
+        spawn_enemy(&mut world, UVec2::new(2, 2));
+        assert_eq!(
+            click(&mut world, UVec2::new(2, 2)),
+            vec![PlayerAction::Move { dx: 1, dy: 1 }]
+        );
+        assert!(!is_travelling(&world));
+    }
+
+    #[test]
+    fn clicking_an_unknown_tile_is_ignored() {
+        let mut world = test_world();
+        see_columns(&mut world, 4);
+        assert!(click(&mut world, UVec2::new(6, 1)).is_empty());
+        assert!(!is_travelling(&world));
+    }
+
+    #[test]
+    fn travels_over_the_known_tiles_only() {
+        let mut world = test_world();
+        see_columns(&mut world, 4);
+        // seen before, but the way there wasn't
+        reveal(&mut world, &[UVec2::new(6, 1)]);
+        assert!(click(&mut world, UVec2::new(6, 1)).is_empty());
+        assert!(!is_travelling(&world));
+
+        reveal(&mut world, &[UVec2::new(4, 2), UVec2::new(5, 2)]);
+        click(&mut world, UVec2::new(6, 1));
+        let path = travel_path(&world);
+        assert_eq!(path.len(), 5);
+        assert_eq!(
+            path[2..],
+            [UVec2::new(4, 2), UVec2::new(5, 2), UVec2::new(6, 1)]
+        );
+    }
+}
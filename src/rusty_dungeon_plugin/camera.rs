@@ -22,6 +22,17 @@ impl Camera {
         }
     }
 
+    /// Changes the number of tiles shown around the same center
+    pub fn resize(&mut self, display_width: i32, display_height: i32) {
+        let center = IVec2::new(
+            self.left_x + self.display_width / 2,
+            self.top_y + self.display_height / 2,
+        );
+        self.display_width = display_width;
+        self.display_height = display_height;
+        self.on_player_move(center);
+    }
+
     pub fn on_player_move(&mut self, player_position: IVec2) {
         self.left_x = player_position.x - (self.display_width / 2);
         self.right_x = player_position.x + (self.display_width / 2);
@@ -1,7 +1,7 @@
 use crate::ascii_tilemap_plugin::color::GlyphColor;
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
 
-#[derive(Debug, Clone, Copy, Component, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Component, PartialEq, Eq, Hash)]
 pub struct Position(pub UVec2);
 
 #[derive(Debug, Clone, Copy, Component)]
@@ -19,6 +19,10 @@ pub struct Enemy;
 #[derive(Debug, Component)]
 pub struct MovingRandomly;
 
+/// Moves toward the player when the player is in its field of view
+#[derive(Debug, Component)]
+pub struct ChasingPlayer;
+
 #[derive(Debug, Component)]
 pub struct WantsToMove {
     pub entity: Entity,
@@ -34,8 +38,66 @@ pub struct Health {
 #[derive(Debug, Component)]
 pub struct Name(pub String);
 
+/// Base damage of a creature, or the damage added by a weapon
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Damage(pub i32);
+
+/// Only one weapon can be carried, its `Damage` is added to the damage of its owner
+#[derive(Debug, Component)]
+pub struct Weapon;
+
+/// Only one armor can be carried, it reduces the damage taken by its owner
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Armor {
+    pub defense: i32,
+}
+
+#[derive(Debug, Component)]
+pub struct Item;
+
+/// An item that was picked up, it no longer has a position on the map
+#[derive(Debug, Component)]
+pub struct Carried(pub Entity);
+
+#[derive(Debug, Component)]
+pub struct ProvidesHealing {
+    pub amount: i32,
+}
+
+/// Reveals the whole level
+#[derive(Debug, Component)]
+pub struct ProvidesDungeonMap;
+
+#[derive(Debug, Component)]
+pub struct ActivateItem {
+    pub used_by: Entity,
+    pub item: Entity,
+}
+
+/// The player wins by picking it up on the last level
+#[derive(Debug, Component)]
+pub struct AmuletOfYala;
+
 #[derive(Component)]
 pub struct WantsToAttack {
     pub attacker: Entity,
     pub victim: Entity,
 }
+
+#[derive(Debug, Clone, Component)]
+pub struct FieldOfView {
+    pub visible_tiles: HashSet<UVec2>,
+    pub radius: i32,
+    /// Set when the owner moved and the visible tiles need to be recomputed
+    pub is_dirty: bool,
+}
+
+impl FieldOfView {
+    pub fn new(radius: i32) -> Self {
+        Self {
+            visible_tiles: HashSet::default(),
+            radius,
+            is_dirty: true,
+        }
+    }
+}
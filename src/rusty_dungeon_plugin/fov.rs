@@ -0,0 +1,202 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use super::{components::Position, map::Map};
+
+/// Multipliers used to transform the coordinates of the first octant to each of the 8 octants
+const OCTANTS: [Octant; 8] = [
+    Octant::new(1, 0, 0, 1),
+    Octant::new(0, 1, 1, 0),
+    Octant::new(0, -1, 1, 0),
+    Octant::new(-1, 0, 0, 1),
+    Octant::new(-1, 0, 0, -1),
+    Octant::new(0, -1, -1, 0),
+    Octant::new(0, 1, -1, 0),
+    Octant::new(1, 0, 0, -1),
+];
+
+#[derive(Clone, Copy)]
+struct Octant {
+    xx: i32,
+    xy: i32,
+    yx: i32,
+    yy: i32,
+}
+
+impl Octant {
+    const fn new(xx: i32, xy: i32, yx: i32, yy: i32) -> Self {
+        Self { xx, xy, yx, yy }
+    }
+
+    fn transform(self, origin: IVec2, dx: i32, dy: i32) -> IVec2 {
+        IVec2::new(
+            origin.x + dx * self.xx + dy * self.xy,
+            origin.y + dx * self.yx + dy * self.yy,
+        )
+    }
+}
+
+struct ShadowCaster<'a> {
+    map: &'a Map,
+    origin: IVec2,
+    radius: i32,
+    visible_tiles: HashSet<UVec2>,
+}
+
+impl ShadowCaster<'_> {
+    fn is_opaque(&self, point: IVec2) -> bool {
+        point.x < 0 || point.y < 0 || self.map.is_opaque(Position(point.as_uvec2()))
+    }
+
+    fn light(&mut self, point: IVec2) {
+        if point.x >= 0 && point.y >= 0 && self.map.in_bounds(Position(point.as_uvec2())) {
+            self.visible_tiles.insert(point.as_uvec2());
+        }
+    }
+
+    /// Scans the rows of an octant from `row` outward, bounded by the `start` and `end` slopes.
+    /// Every time a wall is found the scan recurses on the part of the next row that is still lit
+    #[allow(clippy::cast_precision_loss)]
+    fn cast_light(&mut self, row: i32, mut start: f32, end: f32, octant: Octant) {
+        if start < end {
+            return;
+        }
+
+        let radius_squared = self.radius * self.radius;
+        let mut next_start = start;
+        for distance in row..=self.radius {
+            let dy = -distance;
+            let mut blocked = false;
+            for dx in -distance..=0 {
+                let point = octant.transform(self.origin, dx, dy);
+                let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+
+                if start < right_slope {
+                    continue;
+                }
+                if end > left_slope {
+                    break;
+                }
+
+                if dx * dx + dy * dy <= radius_squared {
+                    self.light(point);
+                }
+
+                if blocked {
+                    if self.is_opaque(point) {
+                        next_start = right_slope;
+                    } else {
+                        blocked = false;
+                        start = next_start;
+                    }
+                } else if self.is_opaque(point) && distance < self.radius {
+                    blocked = true;
+                    self.cast_light(distance + 1, start, left_slope, octant);
+                    next_start = right_slope;
+                }
+            }
+
+            if blocked {
+                break;
+            }
+        }
+    }
+}
+
+/// Computes the tiles visible from the origin using recursive shadowcasting.
+/// Walls are visible but block the view of anything behind them.
+pub fn field_of_view(origin: UVec2, radius: i32, map: &Map) -> HashSet<UVec2> {
+    let mut caster = ShadowCaster {
+        map,
+        origin: origin.as_ivec2(),
+        radius,
+        visible_tiles: HashSet::default(),
+    };
+    caster.light(caster.origin);
+    for octant in OCTANTS {
+        caster.cast_light(1, 1.0, 0.0, octant);
+    }
+    caster.visible_tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_dungeon_plugin::map::TileType;
+
+    fn map_from_str(text: &str) -> Map {
+        let rows = text
+            .lines()
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<_>>();
+        #[allow(clippy::cast_possible_truncation)]
+        let mut map = Map::new(rows[0].len() as u32, rows.len() as u32);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, tile) in row.chars().enumerate() {
+                if tile == '#' {
+                    #[allow(clippy::cast_possible_truncation)]
+                    map.set_tile(Position(UVec2::new(x as u32, y as u32)), TileType::Wall);
+                }
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn open_room_is_fully_visible() {
+        let map = map_from_str(
+            "
+            #####
+            #...#
+            #...#
+            #...#
+            #####
+            ",
+        );
+        let visible = field_of_view(UVec2::new(2, 2), 8, &map);
+        assert_eq!(visible.len(), 25);
+    }
+
+    #[test]
+    fn walls_block_vision() {
+        let map = map_from_str(
+            "
+            .......
+            ...#...
+            .......
+            ",
+        );
+        let visible = field_of_view(UVec2::new(3, 2), 8, &map);
+        assert!(visible.contains(&UVec2::new(3, 1)));
+        assert!(!visible.contains(&UVec2::new(3, 0)));
+        assert!(visible.contains(&UVec2::new(0, 0)));
+        assert!(visible.contains(&UVec2::new(6, 0)));
+    }
+
+    #[test]
+    fn walls_hide_adjacent_room() {
+        let map = map_from_str(
+            "
+            #########
+            #...#...#
+            #...#...#
+            #...#...#
+            #########
+            ",
+        );
+        let visible = field_of_view(UVec2::new(2, 2), 8, &map);
+        assert!(visible.contains(&UVec2::new(4, 2)));
+        assert!(!visible.contains(&UVec2::new(5, 2)));
+        assert!(!visible.contains(&UVec2::new(6, 1)));
+        assert!(!visible.contains(&UVec2::new(7, 3)));
+    }
+
+    #[test]
+    fn radius_limits_vision() {
+        let map = Map::new(20, 1);
+        let visible = field_of_view(UVec2::new(0, 0), 5, &map);
+        assert!(visible.contains(&UVec2::new(5, 0)));
+        assert!(!visible.contains(&UVec2::new(6, 0)));
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::ascii_tilemap_plugin::rich_text::{plain, RichChar};
+
+/// Older entries are dropped once the log holds this many
+pub const MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub color: Color,
+    /// Glyphs without a color of their own use the color of the entry
+    pub text: Vec<RichChar>,
+}
+
+/// Messages shown to the player, the newest is at the back
+#[derive(Debug, Default)]
+pub struct GameLog {
+    entries: VecDeque<LogEntry>,
+    /// How many entries the view is scrolled back from the newest one
+    scroll: usize,
+}
+
+impl GameLog {
+    /// Adds a plain text, it can contain names and paths so markup isn't parsed
+    pub fn add<S: Into<String>>(&mut self, color: Color, text: S) {
+        self.add_rich(color, plain(&text.into()));
+    }
+
+    /// Adds glyphs built with `rich_text::parse` and `rich_text::plain`
+    pub fn add_rich(&mut self, color: Color, text: Vec<RichChar>) {
+        self.entries.push_back(LogEntry { color, text });
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        // keep looking at the same entries when scrolled back
+        if self.scroll > 0 {
+            self.scroll = usize::min(self.scroll + 1, self.max_scroll());
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.scroll = 0;
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    /// Scrolls back toward older entries
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.scroll = usize::min(self.scroll + lines, self.max_scroll());
+    }
+
+    /// Scrolls forward toward the newest entry
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// The entries that fit in a panel of the given height, oldest first
+    pub fn visible(&self, height: usize) -> impl Iterator<Item = &LogEntry> {
+        let end = self.entries.len() - self.scroll;
+        let start = end.saturating_sub(height);
+        self.entries.range(start..end)
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.entries.len().saturating_sub(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_with(count: usize) -> GameLog {
+        let mut log = GameLog::default();
+        for i in 0..count {
+            log.add(Color::WHITE, i.to_string());
+        }
+        log
+    }
+
+    fn text(entry: &LogEntry) -> String {
+        entry.text.iter().map(|c| c.glyph).collect()
+    }
+
+    fn visible_texts(log: &GameLog, height: usize) -> Vec<String> {
+        log.visible(height).map(text).collect()
+    }
+
+    #[test]
+    fn log_is_bounded() {
+        let log = log_with(MAX_ENTRIES + 10);
+        assert_eq!(log.entries.len(), MAX_ENTRIES);
+        assert_eq!(log.entries.front().map(text).as_deref(), Some("10"));
+    }
+
+    #[test]
+    fn visible_shows_newest_entries() {
+        let log = log_with(5);
+        assert_eq!(visible_texts(&log, 3), vec!["2", "3", "4"]);
+        assert_eq!(visible_texts(&log, 10), vec!["0", "1", "2", "3", "4"]);
+    }
+
+    #[test]
+    fn scrolling() {
+        let mut log = log_with(5);
+        log.scroll_up(2);
+        assert_eq!(visible_texts(&log, 3), vec!["0", "1", "2"]);
+        log.scroll_up(100);
+        assert_eq!(log.scroll(), 4);
+        assert_eq!(visible_texts(&log, 3), vec!["0"]);
+        log.scroll_down(100);
+        assert_eq!(log.scroll(), 0);
+        assert_eq!(visible_texts(&log, 3), vec!["2", "3", "4"]);
+    }
+
+    #[test]
+    fn new_entries_keep_scrolled_view() {
+        let mut log = log_with(5);
+        log.scroll_up(1);
+        log.add(Color::WHITE, "5");
+        assert_eq!(visible_texts(&log, 2), vec!["2", "3"]);
+    }
+
+    #[test]
+    fn markup_is_not_parsed() {
+        let mut log = GameLog::default();
+        log.add(Color::WHITE, "#[red]Orc#[]");
+        assert_eq!(visible_texts(&log, 1), vec!["#[red]Orc#[]"]);
+        assert!(log.entries[0].text.iter().all(|c| c.foreground.is_none()));
+    }
+}
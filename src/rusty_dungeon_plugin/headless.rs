@@ -0,0 +1,425 @@
+use std::{collections::VecDeque, fmt};
+
+use bevy::{
+    input::{keyboard::KeyboardInput, ElementState, InputPlugin},
+    prelude::*,
+};
+
+use super::{
+    components::{Carried, Enemy, Health, Player},
+    tilemap_builder, DungeonDepth, DungeonLogicPlugin, DungeonRenderPlugin, TurnCounter, TurnState,
+};
+use crate::ascii_tilemap_plugin::{AsciiTilemapPlugin, Backend};
+use crate::game_rng::GameRng;
+
+/// Keys pressed by `ScriptedInput::random` once the script is over, moving is more likely
+const RANDOM_KEYS: [KeyCode; 11] = [
+    KeyCode::W,
+    KeyCode::A,
+    KeyCode::S,
+    KeyCode::D,
+    KeyCode::W,
+    KeyCode::A,
+    KeyCode::S,
+    KeyCode::D,
+    KeyCode::G,
+    KeyCode::Key1,
+    KeyCode::Period,
+];
+/// A turn takes a few frames, the simulation gives up after this many frames per turn
+const MAX_UPDATES_PER_TURN: u32 = 10;
+
+/// Keys fed to `player_input` instead of a keyboard
+#[derive(Debug, Default)]
+pub struct ScriptedInput {
+    keys: VecDeque<KeyCode>,
+    /// Picks random keys once the script is over.
+    /// It's not the game's generator so a recording replays the same game.
+    rng: Option<GameRng>,
+}
+
+impl ScriptedInput {
+    pub fn new<I: IntoIterator<Item = KeyCode>>(keys: I) -> Self {
+        Self {
+            keys: keys.into_iter().collect(),
+            rng: None,
+        }
+    }
+
+    pub fn random(seed: u64) -> Self {
+        Self {
+            keys: VecDeque::new(),
+            rng: Some(GameRng::new(seed)),
+        }
+    }
+
+    fn next(&mut self) -> Option<KeyCode> {
+        self.keys.pop_front().or_else(|| {
+            self.rng
+                .as_mut()
+                .map(|rng| RANDOM_KEYS[rng.rng().usize(..RANDOM_KEYS.len())])
+        })
+    }
+}
+
+/// Presses the next scripted key while the game awaits input
+pub fn scripted_input(
+    mut input: ResMut<ScriptedInput>,
+    turn_state: Res<State<TurnState>>,
+    mut keyboard_input_events: EventWriter<KeyboardInput>,
+) {
+    // puffin::profile_function!();
+    if *turn_state.current() != TurnState::AwaitingInput {
+        return;
+    }
+    if let Some(key_code) = input.next() {
+        for state in [ElementState::Pressed, ElementState::Released] {
+            keyboard_input_events.send(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key_code),
+                state,
+            });
+        }
+    }
+}
+
+/// Builds the game without a window, call `App::update` to step it
+pub fn headless_app(rng: GameRng, input: ScriptedInput) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(InputPlugin)
+        .insert_resource(rng)
+        .insert_resource(input)
+        .add_plugin(DungeonLogicPlugin)
+        .add_system_to_stage(CoreStage::PreUpdate, scripted_input);
+    app
+}
+
+/// Draws the game of a `headless_app` in a `VirtualConsole` resource
+pub fn add_virtual_console(app: &mut App) {
+    app.insert_resource(tilemap_builder())
+        .add_plugin(AsciiTilemapPlugin::new(Backend::Virtual))
+        .add_plugin(DungeonRenderPlugin);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Alive,
+    Dead,
+    Won,
+}
+
+/// State of a game at the end of a headless run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub seed: u64,
+    pub turns: u32,
+    pub depth: u32,
+    pub outcome: Outcome,
+    pub health: i32,
+    pub max_health: i32,
+    pub monsters_left: usize,
+    pub items_carried: usize,
+}
+
+impl Summary {
+    pub fn from_world(world: &mut World) -> Self {
+        let (health, max_health) = world
+            .query_filtered::<&Health, With<Player>>()
+            .iter(world)
+            .next()
+            .map_or((0, 0), |health| (health.current, health.max));
+        let outcome = match world.get_resource::<State<TurnState>>().map(State::current) {
+            Some(TurnState::GameOver) => Outcome::Dead,
+            Some(TurnState::Victory) => Outcome::Won,
+            _ => Outcome::Alive,
+        };
+        Self {
+            seed: world.get_resource::<GameRng>().map_or(0, GameRng::seed),
+            turns: world
+                .get_resource::<TurnCounter>()
+                .map_or(0, |turns| turns.0),
+            depth: world
+                .get_resource::<DungeonDepth>()
+                .map_or(0, |depth| depth.0),
+            outcome,
+            health,
+            max_health,
+            monsters_left: world
+                .query_filtered::<Entity, With<Enemy>>()
+                .iter(world)
+                .count(),
+            items_carried: world.query::<&Carried>().iter(world).count(),
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "seed: {}", self.seed)?;
+        writeln!(f, "turns: {}", self.turns)?;
+        writeln!(f, "outcome: {:?}", self.outcome)?;
+        writeln!(f, "dungeon level: {}", self.depth)?;
+        writeln!(f, "health: {} / {}", self.health, self.max_health)?;
+        writeln!(f, "monsters left on the level: {}", self.monsters_left)?;
+        write!(f, "items carried: {}", self.items_carried)
+    }
+}
+
+/// Plays up to the given number of turns, stops early when the game is over
+pub fn run_headless(app: &mut App, turns: u32) -> Summary {
+    for _ in 0..turns.saturating_add(1).saturating_mul(MAX_UPDATES_PER_TURN) {
+        app.update();
+        let summary = Summary::from_world(&mut app.world);
+        if summary.turns >= turns || summary.outcome != Outcome::Alive {
+            return summary;
+        }
+    }
+    warn!("The simulation stopped before playing {} turns", turns);
+    Summary::from_world(&mut app.world)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ascii_tilemap_plugin::{RenderStats, VirtualConsole};
+    use crate::rusty_dungeon_plugin::components::{AmuletOfYala, Position};
+    use crate::rusty_dungeon_plugin::{RedrawPolicy, RestartEvent};
+    use crate::LayerId;
+    use ntest::timeout;
+
+    fn player_position(app: &mut App) -> UVec2 {
+        app.world
+            .query_filtered::<&Position, With<Player>>()
+            .iter(&app.world)
+            .next()
+            .expect("no player")
+            .0
+    }
+
+    fn player(app: &mut App) -> Entity {
+        app.world
+            .query_filtered::<Entity, With<Player>>()
+            .iter(&app.world)
+            .next()
+            .expect("no player")
+    }
+
+    /// A game where the player has no health left and takes a step
+    fn dead_player_app() -> App {
+        let mut app = headless_app(GameRng::new(42), ScriptedInput::default());
+        app.update();
+        let player = player(&mut app);
+        app.world
+            .get_mut::<Health>(player)
+            .expect("the player has no health")
+            .current = 0;
+        app.insert_resource(ScriptedInput::new([KeyCode::W]));
+        app
+    }
+
+    #[test]
+    #[timeout(10000)]
+    fn plays_the_given_number_of_turns() {
+        let mut app = headless_app(GameRng::new(42), ScriptedInput::random(42));
+        let summary = run_headless(&mut app, 20);
+        assert!(summary.turns == 20 || summary.outcome != Outcome::Alive);
+        assert_eq!(summary.seed, 42);
+    }
+
+    #[test]
+    #[timeout(10000)]
+    fn same_seed_same_game() {
+        let a = run_headless(
+            &mut headless_app(GameRng::new(7), ScriptedInput::random(7)),
+            30,
+        );
+        let b = run_headless(
+            &mut headless_app(GameRng::new(7), ScriptedInput::random(7)),
+            30,
+        );
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    #[timeout(10000)]
+    fn each_key_takes_a_turn() {
+        let mut app = headless_app(
+            GameRng::new(42),
+            ScriptedInput::new([KeyCode::Space, KeyCode::Space, KeyCode::Space]),
+        );
+        let summary = run_headless(&mut app, 10);
+        assert_eq!(summary.turns, 3);
+    }
+
+    #[test]
+    #[timeout(10000)]
+    fn waiting_keeps_the_player_in_place() {
+        let mut app = headless_app(GameRng::new(42), ScriptedInput::new([KeyCode::Space; 5]));
+        app.update();
+        let start = player_position(&mut app);
+        for _ in 0..20 {
+            app.update();
+        }
+        assert_eq!(player_position(&mut app), start);
+        let turns = app.world.get_resource::<TurnCounter>().map(|turns| turns.0);
+        assert_eq!(turns, Some(5));
+    }
+
+    #[test]
+    #[timeout(10000)]
+    fn the_hud_is_drawn() {
+        let mut app = headless_app(GameRng::new(42), ScriptedInput::default());
+        add_virtual_console(&mut app);
+        for _ in 0..3 {
+            app.update();
+        }
+        let console = app
+            .world
+            .get_resource::<VirtualConsole>()
+            .expect("no virtual console");
+        assert!(console.screen().row(0).contains("Health: 20 / 20"));
+        let hud = console.layer(LayerId::Hud).expect("no hud layer");
+        assert!(hud.row(2).starts_with(" Dungeon Level: 1"));
+        // the entities have half the resolution of the hud
+        let entities = console.layer(LayerId::Entities).expect("no entities layer");
+        assert_eq!(entities.size * 2, console.screen().size);
+        assert!(entities.to_string().contains('@'));
+    }
+
+    #[test]
+    #[timeout(10000)]
+    fn the_minimap_is_toggled() {
+        let mut app = headless_app(GameRng::new(42), ScriptedInput::default());
+        add_virtual_console(&mut app);
+        for _ in 0..3 {
+            app.update();
+        }
+        let is_shown = |app: &App| {
+            let console = app
+                .world
+                .get_resource::<VirtualConsole>()
+                .expect("no virtual console");
+            let minimap = console.layer(LayerId::Minimap).expect("no minimap layer");
+            minimap.to_string().contains('@')
+        };
+        // hidden at first, the virtual console shows nothing of a hidden layer
+        assert!(!is_shown(&app));
+
+        app.insert_resource(ScriptedInput::new([KeyCode::M]));
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(is_shown(&app));
+        assert_eq!(
+            app.world.get_resource::<TurnCounter>().map(|turns| turns.0),
+            Some(0)
+        );
+
+        app.insert_resource(ScriptedInput::new([KeyCode::Tab]));
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(!is_shown(&app));
+    }
+
+    #[test]
+    #[timeout(10000)]
+    fn game_over_screen() {
+        let mut app = dead_player_app();
+        add_virtual_console(&mut app);
+        let summary = run_headless(&mut app, 5);
+        assert_eq!(summary.outcome, Outcome::Dead);
+        assert_eq!(summary.turns, 1);
+
+        // the end screen is drawn, then its commands are applied
+        app.update();
+        app.update();
+        let console = app
+            .world
+            .get_resource::<VirtualConsole>()
+            .expect("no virtual console");
+        assert!(console
+            .screen()
+            .to_string()
+            .contains("Your quest has ended."));
+        // the dungeon isn't drawn under the end screen
+        let entities = console.layer(LayerId::Entities).expect("no entities layer");
+        assert!(!entities.to_string().contains('@'));
+    }
+
+    #[test]
+    #[timeout(10000)]
+    fn victory_on_the_amulet() {
+        let mut app = headless_app(GameRng::new(42), ScriptedInput::default());
+        app.update();
+        let position = Position(player_position(&mut app));
+        app.world.spawn().insert(AmuletOfYala).insert(position);
+        app.insert_resource(ScriptedInput::new([KeyCode::Space]));
+        let summary = run_headless(&mut app, 5);
+        assert_eq!(summary.outcome, Outcome::Won);
+        assert_eq!(summary.turns, 1);
+    }
+
+    #[test]
+    #[timeout(10000)]
+    fn restart_starts_a_fresh_level() {
+        let mut app = dead_player_app();
+        assert_eq!(run_headless(&mut app, 5).outcome, Outcome::Dead);
+        let dead_player = player(&mut app);
+        app.insert_resource(DungeonDepth(3));
+
+        app.world
+            .get_resource_mut::<Events<RestartEvent>>()
+            .expect("no restart events")
+            .send(RestartEvent);
+        app.update();
+        app.update();
+
+        let summary = Summary::from_world(&mut app.world);
+        assert_eq!(summary.outcome, Outcome::Alive);
+        assert_eq!(summary.turns, 0);
+        assert_eq!(summary.depth, 1);
+        assert_eq!(summary.health, summary.max_health);
+        assert!(summary.monsters_left > 0);
+        assert!(app.world.get_entity(dead_player).is_none());
+        let players = app
+            .world
+            .query_filtered::<Entity, With<Player>>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(players, 1);
+        let state = app
+            .world
+            .get_resource::<State<TurnState>>()
+            .map(State::current);
+        assert_eq!(state, Some(&TurnState::AwaitingInput));
+    }
+
+    /// Plays the same game with a redraw policy, the stats of the tiles that would be updated
+    fn render_stats(policy: RedrawPolicy) -> RenderStats {
+        let mut app = headless_app(GameRng::new(42), ScriptedInput::random(42));
+        app.insert_resource(policy);
+        add_virtual_console(&mut app);
+        run_headless(&mut app, 30);
+        *app.world
+            .get_resource::<RenderStats>()
+            .expect("no render stats")
+    }
+
+    #[test]
+    #[timeout(20000)]
+    fn redraws_only_the_changed_tiles() {
+        let every_frame = render_stats(RedrawPolicy::EveryFrame);
+        let on_change = render_stats(RedrawPolicy::OnChange);
+        assert_eq!(every_frame.frames, on_change.frames);
+        assert_eq!(every_frame.tiles, on_change.tiles);
+        // both show the same screens, a frame cleared and drawn again the same changes nothing
+        assert_eq!(every_frame.tiles_changed, on_change.tiles_changed);
+        // a full render touches every tile of every frame, a clear only resets the tiles
+        // drawn since the last clear and the moves that scroll the map change a fraction of them
+        assert!(every_frame.tiles_compared < every_frame.tiles / 4);
+        assert!(every_frame.tiles_changed < every_frame.tiles_compared);
+        assert!(on_change.tiles_compared <= every_frame.tiles_compared);
+    }
+}
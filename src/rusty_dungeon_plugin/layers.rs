@@ -0,0 +1,53 @@
+use crate::ascii_tilemap_plugin::{LayerDataBuilder, ResizePolicy, TilemapBuilder};
+use crate::{LayerId, DISPLAY_HEIGHT, DISPLAY_WIDTH, HEIGHT, TILE_HEIGHT, TILE_WIDTH, WIDTH};
+
+/// The layers of the dungeon crawler, only the map and the minimap have a size.
+/// The other layers fill the map with their tiles, the 16 pixel font of the hud
+/// gives it two rows and columns for each tile of the map.
+/// The minimap shows the whole level in the bottom left corner above the log panel,
+/// the terminal stretches it over the screen like the other layers.
+#[allow(clippy::cast_precision_loss)]
+pub fn tilemap_builder() -> TilemapBuilder {
+    TilemapBuilder::new()
+        .resize_policy(ResizePolicy::Resize)
+        .with_layer(
+            LayerDataBuilder::new(LayerId::Map)
+                .texture_path("dungeonfont.png")
+                .size(DISPLAY_WIDTH, DISPLAY_HEIGHT)
+                .tile_size(TILE_WIDTH as f32, TILE_HEIGHT as f32),
+        )
+        .with_layer(
+            LayerDataBuilder::new(LayerId::Entities)
+                .texture_path("dungeonfont.png")
+                .tile_size(TILE_WIDTH as f32, TILE_HEIGHT as f32)
+                .is_transparent(true)
+                .is_background_transparent(true),
+        )
+        .with_layer(
+            LayerDataBuilder::new(LayerId::Hud)
+                .texture_path("16x16-sb-ascii.png")
+                .tile_size(16., 16.)
+                .is_transparent(true)
+                .is_background_transparent(false),
+        )
+        .with_layer(
+            LayerDataBuilder::new(LayerId::Diagnostic)
+                .texture_path("16x16-sb-ascii.png")
+                .tile_size(16., 16.)
+                .is_transparent(true)
+                .is_background_transparent(true)
+                // the fps and the seed stay readable over the minimap
+                .z_order(LayerId::Minimap as u16 + 1),
+        )
+        .with_layer(
+            LayerDataBuilder::new(LayerId::Minimap)
+                .texture_path("16x16-sb-ascii.png")
+                .size(WIDTH, HEIGHT)
+                .tile_size(16., 16.)
+                .scale(0.25)
+                .offset(8., 104.)
+                .is_fixed_size(true)
+                .is_visible(false),
+        )
+        .build()
+}
@@ -1,23 +1,52 @@
-use crate::ascii_tilemap_plugin::geometry::Rect;
 use crate::{HEIGHT, WIDTH};
-use anyhow::{bail, Result};
+use anyhow::{bail, Error, Result};
 use bevy::prelude::*;
-use fastrand::Rng;
-use std::ops::Range;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 use super::components::Position;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
+    /// Stairs leading to the next level
+    Exit,
+}
+
+/// Whether a diagonal step can go around the corner of a wall.
+/// The resource is the rule of new games, a game in progress follows the rule of its map
+/// so a saved game keeps its own.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CornerCutting {
+    Allow,
+    /// Diagonal steps are blocked when either orthogonal neighbour is a wall
+    #[default]
+    Forbid,
+}
+
+impl FromStr for CornerCutting {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match text {
+            "allow" => Ok(CornerCutting::Allow),
+            "forbid" => Ok(CornerCutting::Forbid),
+            _ => bail!(
+                "unknown corner cutting rule {:?}, expected allow or forbid",
+                text
+            ),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct Map {
     tiles: Vec<TileType>,
+    revealed_tiles: Vec<bool>,
     width: u32,
     height: u32,
+    corner_cutting: CornerCutting,
 }
 
 impl Default for Map {
@@ -32,9 +61,31 @@ impl Map {
             width,
             height,
             tiles: vec![TileType::Floor; (width * height) as usize],
+            revealed_tiles: vec![false; (width * height) as usize],
+            corner_cutting: CornerCutting::default(),
         }
     }
 
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn corner_cutting(&self) -> CornerCutting {
+        self.corner_cutting
+    }
+
+    pub fn set_corner_cutting(&mut self, corner_cutting: CornerCutting) {
+        self.corner_cutting = corner_cutting;
+    }
+
+    pub fn fill(&mut self, tile: TileType) {
+        self.tiles.fill(tile);
+    }
+
     pub fn set_tile(&mut self, position: Position, tile: TileType) {
         if let Some(index) = self.try_index(position) {
             self.tiles[index] = tile;
@@ -50,126 +101,108 @@ impl Map {
     }
 
     pub fn can_enter_tile(&self, point: Position) -> bool {
-        self.in_bounds(point) && self.get_tile(point) == Some(TileType::Floor)
+        self.in_bounds(point)
+            && matches!(self.get_tile(point), Some(TileType::Floor | TileType::Exit))
     }
 
-    fn try_index(&self, point: Position) -> Option<usize> {
-        if self.in_bounds(point) {
-            Some(((point.0.y * self.width) + point.0.x) as usize)
-        } else {
-            None
+    /// Like `can_enter_tile` for a step from a neighbour, diagonal steps follow the corner cutting rule
+    pub fn can_enter_tile_from(&self, from: Position, point: Position) -> bool {
+        if !self.can_enter_tile(point) {
+            return false;
         }
-    }
-}
-
-#[allow(clippy::module_name_repetitions)]
-pub struct MapBuilder<'a> {
-    rooms: Vec<Rect>,
-    width: u32,
-    height: u32,
-    room_size: Range<u32>,
-    rng: &'a mut Rng,
-}
-
-impl<'a> MapBuilder<'a> {
-    pub fn new(
-        room_count: u32,
-        width: u32,
-        height: u32,
-        room_size: Range<u32>,
-        rng: &'a mut Rng,
-    ) -> Self {
-        Self {
-            rooms: Vec::with_capacity(room_count as usize),
-            width,
-            height,
-            room_size,
-            rng,
+        let is_diagonal = from.0.x != point.0.x && from.0.y != point.0.y;
+        if !is_diagonal || self.corner_cutting == CornerCutting::Allow {
+            return true;
         }
+        self.can_enter_tile(Position(UVec2::new(point.0.x, from.0.y)))
+            && self.can_enter_tile(Position(UVec2::new(from.0.x, point.0.y)))
     }
 
-    fn build_random_rooms(&mut self, map: &mut Map) {
-        let mut iteration = 0;
-        let room_count = self.rooms.capacity();
-        while self.rooms.len() < room_count && iteration < room_count * 2 {
-            let room = Rect::with_dimension(
-                self.rng.u32(0..self.width - self.room_size.end),
-                self.rng.u32(0..self.height - self.room_size.end),
-                self.rng.u32(self.room_size.clone()),
-                self.rng.u32(self.room_size.clone()),
-            );
-
-            if !self.rooms.iter().any(|r| r.intersect(&room)) {
-                for point in room.points() {
-                    map.set_tile(Position(point), TileType::Floor);
-                }
-                self.rooms.push(room);
-            }
-            iteration += 1;
-        }
+    /// Returns the neighbours of a point that can be entered, diagonals included
+    pub fn available_exits(&self, point: UVec2) -> Vec<UVec2> {
+        [
+            IVec2::new(-1, 0),
+            IVec2::new(1, 0),
+            IVec2::new(0, -1),
+            IVec2::new(0, 1),
+            IVec2::new(-1, -1),
+            IVec2::new(1, -1),
+            IVec2::new(-1, 1),
+            IVec2::new(1, 1),
+        ]
+        .into_iter()
+        .map(|delta| point.as_ivec2() + delta)
+        .filter(|exit| exit.x >= 0 && exit.y >= 0)
+        .map(|exit| exit.as_uvec2())
+        .filter(|exit| self.can_enter_tile_from(Position(point), Position(*exit)))
+        .collect()
     }
 
-    fn build_vertical_tunnels(map: &mut Map, y1: u32, y2: u32, x: u32) {
-        use std::cmp::{max, min};
-        for y in min(y1, y2)..=max(y1, y2) {
-            map.set_tile(Position(UVec2::new(x, y)), TileType::Floor);
-        }
+    /// Returns true if the tile blocks line of sight, anything out of bounds is considered opaque
+    pub fn is_opaque(&self, point: Position) -> bool {
+        !self.can_enter_tile(point)
     }
 
-    fn build_horizontal_tunnels(map: &mut Map, x1: u32, x2: u32, y: u32) {
-        use std::cmp::{max, min};
-        for x in min(x1, x2)..=max(x1, x2) {
-            map.set_tile(Position(UVec2::new(x, y)), TileType::Floor);
+    /// Marks a tile as seen by the player so it can be drawn once it's out of view
+    pub fn reveal(&mut self, point: Position) {
+        if let Some(index) = self.try_index(point) {
+            self.revealed_tiles[index] = true;
         }
     }
 
-    fn build_tunnels(&mut self, map: &mut Map) {
-        let mut rooms = self.rooms.clone();
-        rooms.sort_by(|a, b| a.center().x.cmp(&b.center().x));
-
-        for (i, room) in rooms.iter().enumerate().skip(1) {
-            let prev = rooms[i - 1].center();
-            let new = room.center();
+    pub fn reveal_all(&mut self) {
+        self.revealed_tiles.fill(true);
+    }
 
-            if self.rng.bool() {
-                Self::build_horizontal_tunnels(map, prev.x, new.x, prev.y);
-                Self::build_vertical_tunnels(map, prev.y, new.y, new.x);
-            } else {
-                Self::build_vertical_tunnels(map, prev.y, new.y, prev.x);
-                Self::build_horizontal_tunnels(map, prev.x, new.x, new.y);
-            }
-        }
+    pub fn is_revealed(&self, point: Position) -> bool {
+        self.try_index(point)
+            .is_some_and(|index| self.revealed_tiles[index])
     }
 
-    pub fn build(&mut self) -> Result<(Map, UVec2, Vec<Rect>)> {
-        if self.width <= self.room_size.end || self.height <= self.room_size.end {
-            bail!(
-                "width and height must be higher than max room_size {}",
-                self.room_size.end
-            );
+    fn try_index(&self, point: Position) -> Option<usize> {
+        if self.in_bounds(point) {
+            Some(((point.0.y * self.width) + point.0.x) as usize)
+        } else {
+            None
         }
-
-        let mut map = Map::new(self.width, self.height);
-        map.tiles.fill(TileType::Wall);
-
-        self.build_random_rooms(&mut map);
-        self.build_tunnels(&mut map);
-
-        let player_start = self.rooms[0].center();
-        Ok((map, player_start, self.rooms.clone()))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ntest::timeout;
+
+    /// 3x3 room with a wall in the top right corner
+    fn test_map(corner_cutting: CornerCutting) -> Map {
+        let mut map = Map::new(3, 3);
+        map.set_tile(Position(UVec2::new(2, 0)), TileType::Wall);
+        map.set_corner_cutting(corner_cutting);
+        map
+    }
+
+    #[test]
+    fn diagonal_steps() {
+        let map = test_map(CornerCutting::Forbid);
+        let center = Position(UVec2::ONE);
+        assert!(map.can_enter_tile_from(center, Position(UVec2::ZERO)));
+        assert!(map.can_enter_tile_from(center, Position(UVec2::new(2, 2))));
+        assert!(!map.can_enter_tile_from(center, Position(UVec2::new(2, 0))));
+        assert_eq!(map.available_exits(UVec2::ONE).len(), 7);
+    }
 
     #[test]
-    #[timeout(50)]
-    fn build() {
-        let mut rng = fastrand::Rng::new();
-        rng.seed(42);
-        assert!(MapBuilder::new(20, 11, 11, 1..2, &mut rng).build().is_ok());
+    fn corner_cutting() {
+        let from = Position(UVec2::new(1, 0));
+        let to = Position(UVec2::new(2, 1));
+        assert!(!test_map(CornerCutting::Forbid).can_enter_tile_from(from, to));
+        assert!(test_map(CornerCutting::Allow).can_enter_tile_from(from, to));
+        assert_eq!(
+            "allow".parse::<CornerCutting>().ok(),
+            Some(CornerCutting::Allow)
+        );
+        assert!("Allow".parse::<CornerCutting>().is_err());
+        // orthogonal steps don't cut corners
+        let below = Position(UVec2::new(1, 1));
+        assert!(test_map(CornerCutting::Forbid).can_enter_tile_from(from, below));
     }
 }
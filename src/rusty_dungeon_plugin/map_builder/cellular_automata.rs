@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use fastrand::Rng;
+
+use super::{
+    closest_to_center, farthest_point, largest_region, random_spawn_points, MapArchitect,
+    MapBuilderResult,
+};
+use crate::rusty_dungeon_plugin::{
+    components::Position,
+    map::{Map, TileType},
+};
+
+const ITERATIONS: u32 = 10;
+/// Chance out of 100 for a tile to start as a floor
+const FLOOR_PERCENT: u32 = 55;
+
+/// Natural looking caves made by smoothing random noise
+pub struct CellularAutomataArchitect;
+
+impl CellularAutomataArchitect {
+    fn random_noise(map: &mut Map, rng: &mut Rng) {
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                let tile = if rng.u32(0..100) < FLOOR_PERCENT {
+                    TileType::Floor
+                } else {
+                    TileType::Wall
+                };
+                map.set_tile(Position(UVec2::new(x, y)), tile);
+            }
+        }
+    }
+
+    fn count_neighbor_walls(map: &Map, point: IVec2) -> usize {
+        (-1..=1)
+            .flat_map(|y| (-1..=1).map(move |x| IVec2::new(x, y)))
+            .filter(|delta| *delta != IVec2::ZERO)
+            .map(|delta| point + delta)
+            .filter(|neighbor| {
+                neighbor.x >= 0
+                    && neighbor.y >= 0
+                    && map.get_tile(Position(neighbor.as_uvec2())) == Some(TileType::Wall)
+            })
+            .count()
+    }
+
+    fn iteration(map: &mut Map) {
+        let mut new_map = map.clone();
+        for y in 1..map.height() - 1 {
+            for x in 1..map.width() - 1 {
+                let point = UVec2::new(x, y);
+                let walls = Self::count_neighbor_walls(map, point.as_ivec2());
+                let tile = if walls > 4 || walls == 0 {
+                    TileType::Wall
+                } else {
+                    TileType::Floor
+                };
+                new_map.set_tile(Position(point), tile);
+            }
+        }
+        *map = new_map;
+    }
+
+    fn build_border(map: &mut Map) {
+        for x in 0..map.width() {
+            map.set_tile(Position(UVec2::new(x, 0)), TileType::Wall);
+            map.set_tile(Position(UVec2::new(x, map.height() - 1)), TileType::Wall);
+        }
+        for y in 0..map.height() {
+            map.set_tile(Position(UVec2::new(0, y)), TileType::Wall);
+            map.set_tile(Position(UVec2::new(map.width() - 1, y)), TileType::Wall);
+        }
+    }
+}
+
+impl MapArchitect for CellularAutomataArchitect {
+    fn build(&mut self, width: u32, height: u32, rng: &mut Rng) -> Result<MapBuilderResult> {
+        let mut map = Map::new(width, height);
+        Self::random_noise(&mut map, rng);
+        for _ in 0..ITERATIONS {
+            Self::iteration(&mut map);
+        }
+        Self::build_border(&mut map);
+
+        // the smaller caves are culled by the builder, the player starts in the biggest one
+        let cave = largest_region(&map);
+        let player_start = closest_to_center(&map, &cave).context("map has no floor tile")?;
+        let spawn_points = random_spawn_points(&map, player_start, rng);
+        let exit = farthest_point(&map, player_start);
+        Ok(MapBuilderResult {
+            map,
+            player_start,
+            spawn_points,
+            exit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rusty_dungeon_plugin::map_builder::{assert_connected, ArchitectKind, MapBuilder};
+    use ntest::timeout;
+
+    #[test]
+    #[timeout(500)]
+    fn build() {
+        let mut rng = fastrand::Rng::new();
+        rng.seed(42);
+        let result = MapBuilder::new(80, 50, ArchitectKind::CellularAutomata, &mut rng)
+            .build()
+            .expect("failed to build the map");
+        assert_connected(&result);
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use fastrand::Rng;
+
+use super::{farthest_point, random_spawn_points, MapArchitect, MapBuilderResult};
+use crate::rusty_dungeon_plugin::{
+    components::Position,
+    map::{Map, TileType},
+};
+
+/// How many steps a drunkard takes before passing out
+const STAGGER_DISTANCE: u32 = 400;
+/// The walk stops when this fraction of the map has been dug
+const DESIRED_FLOOR_DIVISOR: u32 = 3;
+const MAX_DRUNKARDS: u32 = 1000;
+
+/// Winding caves dug by random walkers. Every walker starts on an already dug tile
+/// so the caves are always connected.
+pub struct DrunkardsWalkArchitect;
+
+impl DrunkardsWalkArchitect {
+    #[allow(clippy::cast_possible_wrap)]
+    fn drunkard(start: UVec2, map: &mut Map, rng: &mut Rng) {
+        let mut position = start;
+        for _ in 0..STAGGER_DISTANCE {
+            map.set_tile(Position(position), TileType::Floor);
+            let next = match rng.u8(0..4) {
+                0 => position.as_ivec2() + IVec2::new(-1, 0),
+                1 => position.as_ivec2() + IVec2::new(1, 0),
+                2 => position.as_ivec2() + IVec2::new(0, -1),
+                _ => position.as_ivec2() + IVec2::new(0, 1),
+            };
+            // keep a solid border around the map
+            if next.x < 1
+                || next.y < 1
+                || next.x >= map.width() as i32 - 1
+                || next.y >= map.height() as i32 - 1
+            {
+                break;
+            }
+            position = next.as_uvec2();
+        }
+    }
+
+    fn floor_tiles(map: &Map) -> Vec<UVec2> {
+        (0..map.height())
+            .flat_map(|y| (0..map.width()).map(move |x| UVec2::new(x, y)))
+            .filter(|point| map.can_enter_tile(Position(*point)))
+            .collect()
+    }
+}
+
+impl MapArchitect for DrunkardsWalkArchitect {
+    fn build(&mut self, width: u32, height: u32, rng: &mut Rng) -> Result<MapBuilderResult> {
+        if width < 3 || height < 3 {
+            bail!("width and height must be at least 3");
+        }
+
+        let mut map = Map::new(width, height);
+        map.fill(TileType::Wall);
+
+        let player_start = UVec2::new(width / 2, height / 2);
+        let desired_floor = (width * height / DESIRED_FLOOR_DIVISOR) as usize;
+        Self::drunkard(player_start, &mut map, rng);
+        for _ in 0..MAX_DRUNKARDS {
+            let floor_tiles = Self::floor_tiles(&map);
+            if floor_tiles.len() >= desired_floor {
+                break;
+            }
+            let start = floor_tiles[rng.usize(0..floor_tiles.len())];
+            Self::drunkard(start, &mut map, rng);
+        }
+
+        let spawn_points = random_spawn_points(&map, player_start, rng);
+        let exit = farthest_point(&map, player_start);
+        Ok(MapBuilderResult {
+            map,
+            player_start,
+            spawn_points,
+            exit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rusty_dungeon_plugin::map_builder::{assert_connected, ArchitectKind, MapBuilder};
+    use ntest::timeout;
+
+    #[test]
+    #[timeout(500)]
+    fn build() {
+        let mut rng = fastrand::Rng::new();
+        rng.seed(42);
+        let result = MapBuilder::new(80, 50, ArchitectKind::DrunkardsWalk, &mut rng)
+            .build()
+            .expect("failed to build the map");
+        assert_connected(&result);
+    }
+}
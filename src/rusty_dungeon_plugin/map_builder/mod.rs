@@ -0,0 +1,269 @@
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use fastrand::Rng;
+
+use super::{
+    components::Position,
+    map::{CornerCutting, Map, TileType},
+    pathfinding::DijkstraMap,
+};
+use cellular_automata::CellularAutomataArchitect;
+use drunkard::DrunkardsWalkArchitect;
+use rooms::RoomsArchitect;
+
+mod cellular_automata;
+mod drunkard;
+mod rooms;
+
+const NUM_SPAWN_POINTS: usize = 20;
+/// Minimum distance from the player for spawn points that aren't tied to rooms
+const MIN_SPAWN_DISTANCE: u32 = 10;
+
+pub struct MapBuilderResult {
+    pub map: Map,
+    pub player_start: UVec2,
+    pub spawn_points: Vec<UVec2>,
+    /// Where the stairs to the next level, or the amulet on the last level, should be placed
+    pub exit: UVec2,
+}
+
+/// A map generation algorithm
+pub trait MapArchitect {
+    fn build(&mut self, width: u32, height: u32, rng: &mut Rng) -> Result<MapBuilderResult>;
+}
+
+/// Selects which `MapArchitect` is used to generate the dungeon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArchitectKind {
+    Rooms,
+    CellularAutomata,
+    DrunkardsWalk,
+    #[default]
+    Random,
+}
+
+impl ArchitectKind {
+    fn architect(self, rng: &mut Rng) -> Box<dyn MapArchitect> {
+        match self {
+            Self::Rooms => Box::new(RoomsArchitect::default()),
+            Self::CellularAutomata => Box::new(CellularAutomataArchitect),
+            Self::DrunkardsWalk => Box::new(DrunkardsWalkArchitect),
+            Self::Random => match rng.u8(0..3) {
+                0 => Self::Rooms.architect(rng),
+                1 => Self::CellularAutomata.architect(rng),
+                _ => Self::DrunkardsWalk.architect(rng),
+            },
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct MapBuilder<'a> {
+    width: u32,
+    height: u32,
+    architect: ArchitectKind,
+    corner_cutting: CornerCutting,
+    rng: &'a mut Rng,
+}
+
+impl<'a> MapBuilder<'a> {
+    pub fn new(width: u32, height: u32, architect: ArchitectKind, rng: &'a mut Rng) -> Self {
+        Self {
+            width,
+            height,
+            architect,
+            corner_cutting: CornerCutting::default(),
+            rng,
+        }
+    }
+
+    /// The rule followed by diagonal steps on the map, it decides which tiles can be reached
+    pub fn corner_cutting(mut self, corner_cutting: CornerCutting) -> Self {
+        self.corner_cutting = corner_cutting;
+        self
+    }
+
+    /// Builds the map with the selected architect and makes sure that every floor tile
+    /// and every spawn point can be reached from the player start
+    pub fn build(&mut self) -> Result<MapBuilderResult> {
+        let mut architect = self.architect.architect(self.rng);
+        let mut result = architect.build(self.width, self.height, self.rng)?;
+        result.map.set_corner_cutting(self.corner_cutting);
+
+        if !result.map.can_enter_tile(Position(result.player_start)) {
+            bail!("player start {} is not a floor tile", result.player_start);
+        }
+
+        let dijkstra_map = DijkstraMap::new(&result.map, &[result.player_start]);
+        cull_unreachable(&mut result.map, &dijkstra_map);
+        if let Some(point) = result
+            .spawn_points
+            .iter()
+            .find(|point| dijkstra_map.get(**point).is_none())
+        {
+            bail!(
+                "spawn point {} can't be reached from the player start",
+                point
+            );
+        }
+        if dijkstra_map.get(result.exit).is_none() {
+            bail!(
+                "exit {} can't be reached from the player start",
+                result.exit
+            );
+        }
+
+        Ok(result)
+    }
+}
+
+/// Turns every floor tile that can't be reached into a wall
+fn cull_unreachable(map: &mut Map, dijkstra_map: &DijkstraMap) {
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let point = UVec2::new(x, y);
+            if dijkstra_map.get(point).is_none() {
+                map.set_tile(Position(point), TileType::Wall);
+            }
+        }
+    }
+}
+
+/// Picks random reachable floor tiles that are far enough from the player start
+fn random_spawn_points(map: &Map, player_start: UVec2, rng: &mut Rng) -> Vec<UVec2> {
+    let dijkstra_map = DijkstraMap::new(map, &[player_start]);
+    let mut candidates = (0..map.height())
+        .flat_map(|y| (0..map.width()).map(move |x| UVec2::new(x, y)))
+        .filter(|point| {
+            dijkstra_map
+                .get(*point)
+                .is_some_and(|distance| distance > MIN_SPAWN_DISTANCE)
+        })
+        .collect::<Vec<_>>();
+    rng.shuffle(&mut candidates);
+    candidates.truncate(NUM_SPAWN_POINTS);
+    candidates
+}
+
+/// Returns the reachable tile that is the farthest from the player start
+fn farthest_point(map: &Map, player_start: UVec2) -> UVec2 {
+    DijkstraMap::new(map, &[player_start])
+        .farthest_point()
+        .unwrap_or(player_start)
+}
+
+/// Returns the floor tiles of the biggest area where every tile can be reached from the others
+fn largest_region(map: &Map) -> Vec<UVec2> {
+    let mut is_visited = vec![false; (map.width() * map.height()) as usize];
+    let index = |point: UVec2| (point.y * map.width() + point.x) as usize;
+    let mut largest = vec![];
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let start = UVec2::new(x, y);
+            if is_visited[index(start)] || !map.can_enter_tile(Position(start)) {
+                continue;
+            }
+            is_visited[index(start)] = true;
+            // the region grows while it's being walked through
+            let mut region = vec![start];
+            let mut i = 0;
+            while i < region.len() {
+                for exit in map.available_exits(region[i]) {
+                    if !is_visited[index(exit)] {
+                        is_visited[index(exit)] = true;
+                        region.push(exit);
+                    }
+                }
+                i += 1;
+            }
+            if region.len() > largest.len() {
+                largest = region;
+            }
+        }
+    }
+    largest
+}
+
+/// Returns the point closest to the center of the map
+fn closest_to_center(map: &Map, points: &[UVec2]) -> Option<UVec2> {
+    let center = UVec2::new(map.width() / 2, map.height() / 2).as_vec2();
+    points.iter().copied().min_by(|a, b| {
+        a.as_vec2()
+            .distance_squared(center)
+            .total_cmp(&b.as_vec2().distance_squared(center))
+    })
+}
+
+#[cfg(test)]
+fn assert_connected(result: &MapBuilderResult) {
+    let dijkstra_map = DijkstraMap::new(&result.map, &[result.player_start]);
+    assert!(!result.spawn_points.is_empty());
+    assert_ne!(result.exit, result.player_start);
+    assert!(dijkstra_map.get(result.exit).is_some());
+    for point in &result.spawn_points {
+        assert!(dijkstra_map.get(*point).is_some());
+    }
+    for y in 0..result.map.height() {
+        for x in 0..result.map.width() {
+            let point = UVec2::new(x, y);
+            if result.map.can_enter_tile(Position(point)) {
+                assert!(dijkstra_map.get(point).is_some());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn largest_region_is_picked() {
+        // a small cave in the center and a bigger one on the left
+        let mut map = Map::new(9, 5);
+        map.fill(TileType::Wall);
+        for x in 4..6 {
+            map.set_tile(Position(UVec2::new(x, 2)), TileType::Floor);
+        }
+        for y in 1..4 {
+            map.set_tile(Position(UVec2::new(1, y)), TileType::Floor);
+        }
+        // only connected diagonally when corners can be cut
+        map.set_tile(Position(UVec2::new(2, 0)), TileType::Floor);
+
+        let region = largest_region(&map);
+        assert_eq!(region.len(), 3);
+        assert!(region.iter().all(|point| point.x == 1));
+        assert_eq!(closest_to_center(&map, &region), Some(UVec2::new(1, 2)));
+        assert_eq!(closest_to_center(&map, &[]), None);
+    }
+
+    #[test]
+    fn same_seed_builds_same_map() {
+        for architect in [
+            ArchitectKind::Rooms,
+            ArchitectKind::CellularAutomata,
+            ArchitectKind::DrunkardsWalk,
+            ArchitectKind::Random,
+        ] {
+            let build = || {
+                let mut rng = Rng::new();
+                rng.seed(7);
+                MapBuilder::new(80, 50, architect, &mut rng)
+                    .build()
+                    .expect("failed to build the map")
+            };
+            let a = build();
+            let b = build();
+            assert_eq!(a.player_start, b.player_start);
+            assert_eq!(a.exit, b.exit);
+            assert_eq!(a.spawn_points, b.spawn_points);
+            for y in 0..a.map.height() {
+                for x in 0..a.map.width() {
+                    let position = Position(UVec2::new(x, y));
+                    assert!(a.map.get_tile(position) == b.map.get_tile(position));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::ascii_tilemap_plugin::geometry::Rect;
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use fastrand::Rng;
+use std::ops::Range;
+
+use super::{farthest_point, MapArchitect, MapBuilderResult};
+use crate::rusty_dungeon_plugin::{
+    components::Position,
+    map::{Map, TileType},
+};
+
+const NUM_ROOMS: u32 = 20;
+const MIN_ROOM_SIZE: u32 = 2;
+const MAX_ROOM_SIZE: u32 = 10;
+
+/// Random non overlapping rooms joined by L shaped tunnels
+pub struct RoomsArchitect {
+    room_count: u32,
+    room_size: Range<u32>,
+}
+
+impl Default for RoomsArchitect {
+    fn default() -> Self {
+        Self::new(NUM_ROOMS, MIN_ROOM_SIZE..MAX_ROOM_SIZE)
+    }
+}
+
+impl RoomsArchitect {
+    pub fn new(room_count: u32, room_size: Range<u32>) -> Self {
+        Self {
+            room_count,
+            room_size,
+        }
+    }
+
+    fn build_random_rooms(&self, map: &mut Map, rng: &mut Rng) -> Vec<Rect> {
+        let mut rooms: Vec<Rect> = Vec::with_capacity(self.room_count as usize);
+        let mut iteration = 0;
+        let room_count = rooms.capacity();
+        while rooms.len() < room_count && iteration < room_count * 2 {
+            let room = Rect::with_dimension(
+                rng.u32(0..map.width() - self.room_size.end),
+                rng.u32(0..map.height() - self.room_size.end),
+                rng.u32(self.room_size.clone()),
+                rng.u32(self.room_size.clone()),
+            );
+
+            if !rooms.iter().any(|r| r.intersect(&room)) {
+                for point in room.points() {
+                    map.set_tile(Position(point), TileType::Floor);
+                }
+                rooms.push(room);
+            }
+            iteration += 1;
+        }
+        rooms
+    }
+
+    fn build_vertical_tunnels(map: &mut Map, y1: u32, y2: u32, x: u32) {
+        use std::cmp::{max, min};
+        for y in min(y1, y2)..=max(y1, y2) {
+            map.set_tile(Position(UVec2::new(x, y)), TileType::Floor);
+        }
+    }
+
+    fn build_horizontal_tunnels(map: &mut Map, x1: u32, x2: u32, y: u32) {
+        use std::cmp::{max, min};
+        for x in min(x1, x2)..=max(x1, x2) {
+            map.set_tile(Position(UVec2::new(x, y)), TileType::Floor);
+        }
+    }
+
+    fn build_tunnels(rooms: &[Rect], map: &mut Map, rng: &mut Rng) {
+        let mut rooms = rooms.to_vec();
+        rooms.sort_by(|a, b| a.center().x.cmp(&b.center().x));
+
+        for (i, room) in rooms.iter().enumerate().skip(1) {
+            let prev = rooms[i - 1].center();
+            let new = room.center();
+
+            if rng.bool() {
+                Self::build_horizontal_tunnels(map, prev.x, new.x, prev.y);
+                Self::build_vertical_tunnels(map, prev.y, new.y, new.x);
+            } else {
+                Self::build_vertical_tunnels(map, prev.y, new.y, prev.x);
+                Self::build_horizontal_tunnels(map, prev.x, new.x, new.y);
+            }
+        }
+    }
+}
+
+impl MapArchitect for RoomsArchitect {
+    fn build(&mut self, width: u32, height: u32, rng: &mut Rng) -> Result<MapBuilderResult> {
+        if width <= self.room_size.end || height <= self.room_size.end {
+            bail!(
+                "width and height must be higher than max room_size {}",
+                self.room_size.end
+            );
+        }
+
+        let mut map = Map::new(width, height);
+        map.fill(TileType::Wall);
+
+        let rooms = self.build_random_rooms(&mut map, rng);
+        if rooms.is_empty() {
+            bail!("failed to place any room");
+        }
+        Self::build_tunnels(&rooms, &mut map, rng);
+
+        let player_start = rooms[0].center();
+        let exit = farthest_point(&map, player_start);
+        Ok(MapBuilderResult {
+            map,
+            player_start,
+            spawn_points: rooms.iter().skip(1).map(Rect::center).collect(),
+            exit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_dungeon_plugin::map_builder::{assert_connected, ArchitectKind, MapBuilder};
+    use ntest::timeout;
+
+    #[test]
+    #[timeout(50)]
+    fn build() {
+        let mut rng = fastrand::Rng::new();
+        rng.seed(42);
+        assert!(RoomsArchitect::new(20, 1..2)
+            .build(11, 11, &mut rng)
+            .is_ok());
+    }
+
+    #[test]
+    #[timeout(500)]
+    fn connected() {
+        let mut rng = fastrand::Rng::new();
+        rng.seed(42);
+        let result = MapBuilder::new(80, 50, ArchitectKind::Rooms, &mut rng)
+            .build()
+            .expect("failed to build the map");
+        assert_connected(&result);
+    }
+}
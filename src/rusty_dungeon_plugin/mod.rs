@@ -1,42 +1,101 @@
-use crate::ascii_tilemap_plugin::{geometry::Rect, DrawContext};
+use crate::ascii_tilemap_plugin::{DrawContext, Layer, TilemapLayout};
+use crate::game_rng::GameRng;
+use crate::key_bindings::{KeyBindings, KEY_BINDINGS_PATH};
 use crate::rusty_dungeon_plugin::components::Position;
-use crate::{LayerId, DISPLAY_HEIGHT, DISPLAY_WIDTH, HEIGHT, TILE_HEIGHT, TILE_WIDTH, WIDTH};
+use crate::{LayerId, DISPLAY_HEIGHT, DISPLAY_WIDTH};
 
 use bevy::{
     diagnostic::{Diagnostic, Diagnostics, FrameTimeDiagnosticsPlugin},
+    ecs::schedule::ShouldRun,
     prelude::*,
     utils::Instant,
 };
 
+use auto_travel::{auto_travel, mouse_click, AutoTravel};
 use camera::Camera;
-use map::MapBuilder;
-use spawner::{spawn_monster, spawn_player};
+use components::Carried;
+use game_log::GameLog;
+use map_builder::ArchitectKind;
+use replay::{play_back, record_actions, Playback, PlayerAction, Recorder};
+use save::{continue_game, save_game};
+use spawner::{spawn_level, spawn_player};
+use systems::game_over::{game_over, victory};
+use systems::log_panel::{log_panel, scroll_log};
+use systems::minimap::{minimap, toggle_minimap};
+use systems::player_input::{keyboard_actions, player_input};
 use systems::{
-    combat::combat, end_turn::end_turn, entity_render::entity_render, hud::hud,
-    map_render::map_render, movement::movement, player_input::player_input,
-    random_move::random_move, tooltips::tooltips,
+    chasing::chasing, combat::combat, end_turn::end_turn, entity_render::entity_render, fov::fov,
+    hud::hud, map_render::map_render, movement::movement, next_level::next_level,
+    random_move::random_move, tooltips::tooltips, use_items::use_items,
 };
+use templates::{SpawnTemplates, SPAWNS_PATH};
 
+mod auto_travel;
 mod camera;
 mod components;
+mod fov;
+mod game_log;
+pub mod headless;
+mod layers;
 mod map;
+mod map_builder;
+mod pathfinding;
+pub mod replay;
+mod save;
 mod spawner;
 mod systems;
+mod templates;
+
+pub use layers::tilemap_builder;
+pub use map::CornerCutting;
+pub use save::ContinueGame;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
 struct RenderSystem;
 
-const NUM_ROOMS: u32 = 20;
-const MIN_ROOM_SIZE: u32 = 2;
-const MAX_ROOM_SIZE: u32 = 10;
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct FovSystem;
+
+/// Systems sending a `PlayerAction`
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+struct ActionSystem;
+
+/// The amulet of Yala is on this level
+const FINAL_DEPTH: u32 = 3;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum TurnState {
     AwaitingInput,
     PlayerTurn,
     MonserTurn,
+    NextLevel,
+    GameOver,
+    Victory,
+}
+/// The tiles under the mouse cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CursorPos {
+    /// Tile of the map layer, relative to the camera
+    pub map: Option<UVec2>,
+    /// Tile of the hud layer, where the tooltips are printed
+    pub hud: Option<UVec2>,
+}
+pub struct DungeonDepth(pub u32);
+/// Number of turns played by the player since the start of the game
+#[derive(Debug, Default)]
+pub struct TurnCounter(pub u32);
+
+pub struct RestartEvent;
+
+/// When the dungeon is drawn again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedrawPolicy {
+    #[default]
+    EveryFrame,
+    /// Only on frames where the turn, the camera, the cursor or the log changed,
+    /// the layers keep what was drawn before
+    OnChange,
 }
-pub struct CursorPos(pub Option<UVec2>);
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
 enum Stage {
@@ -45,10 +104,95 @@ enum Stage {
     EndTurn,
 }
 
+/// The dungeon crawler drawn with the ascii tilemap
 pub struct RustyDungeonPlugin;
 impl Plugin for RustyDungeonPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(startup)
+        app.add_plugin(DungeonLogicPlugin)
+            .add_plugin(DungeonRenderPlugin)
+            // the logic plugin uses the default bindings so headless runs don't depend on the file
+            .insert_resource(KeyBindings::load_or_default(KEY_BINDINGS_PATH))
+            // AwaitingInput
+            .add_system_set_to_stage(
+                Stage::BeforeCombat,
+                SystemSet::on_update(TurnState::AwaitingInput)
+                    .with_system(mouse_click.before(ActionSystem)),
+            )
+            .add_system_to_stage(
+                Stage::EndTurn,
+                diagnostic
+                    .with_run_criteria(needs_redraw)
+                    .label(RenderSystem)
+                    .after(FovSystem),
+            )
+            .add_system(scroll_log)
+            .add_system(update_cursor);
+    }
+}
+
+/// Draws the dungeon with the `DrawContext`, it doesn't read the window or the mouse
+/// so it can run headless with `Backend::Virtual`
+pub struct DungeonRenderPlugin;
+impl Plugin for DungeonRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CursorPos>()
+            .init_resource::<RedrawPolicy>()
+            // EndTurn
+            .add_system_set_to_stage(
+                Stage::EndTurn,
+                SystemSet::new()
+                    .with_run_criteria(needs_redraw)
+                    .label(RenderSystem)
+                    .after(FovSystem)
+                    .with_system(hud)
+                    .with_system(map_render)
+                    .with_system(minimap)
+                    .with_system(entity_render)
+                    .with_system(tooltips)
+                    .with_system(log_panel),
+            )
+            // GameOver and Victory, drawn instead of the dungeon
+            .add_system_set_to_stage(
+                Stage::EndTurn,
+                SystemSet::on_update(TurnState::GameOver).with_system(game_over),
+            )
+            .add_system_set_to_stage(
+                Stage::EndTurn,
+                SystemSet::on_update(TurnState::Victory).with_system(victory),
+            )
+            .add_system_to_stage(
+                Stage::EndTurn,
+                clear_screen
+                    .with_run_criteria(needs_redraw)
+                    .before(RenderSystem),
+            )
+            .add_system_to_stage(Stage::EndTurn, fit_camera.before(RenderSystem))
+            .add_system(toggle_minimap);
+    }
+}
+
+/// The turn logic of the dungeon crawler, it doesn't draw anything
+/// so it can run without a window under `MinimalPlugins`
+pub struct DungeonLogicPlugin;
+impl Plugin for DungeonLogicPlugin {
+    fn build(&self, app: &mut App) {
+        let templates = SpawnTemplates::load(SPAWNS_PATH)
+            .unwrap_or_else(|e| panic!("failed to load the spawn templates: {:?}", e));
+        app.init_resource::<ArchitectKind>()
+            .init_resource::<CornerCutting>()
+            .init_resource::<GameRng>()
+            .init_resource::<GameLog>()
+            .init_resource::<TurnCounter>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<Playback>()
+            .init_resource::<Recorder>()
+            .init_resource::<AutoTravel>()
+            .insert_resource(templates)
+            .add_startup_system(startup)
+            .add_startup_system_to_stage(
+                StartupStage::PostStartup,
+                continue_game.exclusive_system(),
+            )
             // Setup stages
             .add_stage(Stage::Combat, SystemStage::parallel())
             .add_stage_before(Stage::Combat, Stage::BeforeCombat, SystemStage::parallel())
@@ -61,19 +205,26 @@ impl Plugin for RustyDungeonPlugin {
             // AwaitingInput
             .add_system_set_to_stage(
                 Stage::BeforeCombat,
-                SystemSet::on_update(TurnState::AwaitingInput).with_system(player_input),
+                SystemSet::on_update(TurnState::AwaitingInput)
+                    .with_system(keyboard_actions.label(ActionSystem))
+                    .with_system(play_back.label(ActionSystem))
+                    .with_system(auto_travel.label(ActionSystem))
+                    .with_system(player_input.after(ActionSystem)),
             )
             // PlayerTurn
             .add_system_set_to_stage(
                 Stage::Combat,
                 SystemSet::on_update(TurnState::PlayerTurn)
                     .with_system(combat)
-                    .with_system(movement),
+                    .with_system(movement)
+                    .with_system(use_items),
             )
             // MonsterTurn
             .add_system_set_to_stage(
                 Stage::BeforeCombat,
-                SystemSet::on_update(TurnState::MonserTurn).with_system(random_move),
+                SystemSet::on_update(TurnState::MonserTurn)
+                    .with_system(random_move)
+                    .with_system(chasing),
             )
             .add_system_set_to_stage(
                 Stage::Combat,
@@ -81,91 +232,175 @@ impl Plugin for RustyDungeonPlugin {
                     .with_system(combat)
                     .with_system(movement),
             )
-            // EndTurn
+            // NextLevel
             .add_system_set_to_stage(
-                Stage::EndTurn,
-                SystemSet::new()
-                    .label(RenderSystem)
-                    .with_system(hud)
-                    .with_system(map_render)
-                    .with_system(entity_render)
-                    .with_system(tooltips)
-                    .with_system(diagnostic),
+                Stage::BeforeCombat,
+                SystemSet::on_update(TurnState::NextLevel).with_system(next_level),
             )
-            .add_system_to_stage(Stage::EndTurn, clear_screen.before(RenderSystem))
+            .add_event::<RestartEvent>()
+            .add_event::<PlayerAction>()
+            .add_system(restart)
+            .add_system(save_game.exclusive_system())
+            .add_system_to_stage(Stage::EndTurn, fov.label(FovSystem))
             .add_system_to_stage(Stage::EndTurn, end_turn)
-            .add_system(update_cursor);
+            .add_system_to_stage(Stage::EndTurn, record_actions);
     }
 }
 
-fn startup(mut commands: Commands) {
+fn startup(
+    mut commands: Commands,
+    architect: Res<ArchitectKind>,
+    corner_cutting: Res<CornerCutting>,
+    templates: Res<SpawnTemplates>,
+    mut log: ResMut<GameLog>,
+    mut rng: ResMut<GameRng>,
+) {
     info!("initializing rusty_dungeon...");
     let start = Instant::now();
+    info!("seed: {}", rng.seed());
 
-    let mut rng = fastrand::Rng::new();
-    rng.seed(42);
-
-    let (map, player_start, rooms) = MapBuilder::new(
-        NUM_ROOMS,
-        WIDTH,
-        HEIGHT,
-        MIN_ROOM_SIZE..MAX_ROOM_SIZE,
-        &mut rng,
-    )
-    .build()
-    .expect("failed to build the map");
-
-    commands.insert_resource(map);
+    let depth = DungeonDepth(1);
+    let player_start = spawn_level(
+        &mut commands,
+        rng.rng(),
+        *architect,
+        *corner_cutting,
+        &templates,
+        depth.0,
+    );
+    commands.insert_resource(depth);
     #[allow(clippy::cast_possible_wrap)]
     commands.insert_resource(Camera::new(
         player_start.as_ivec2(),
         DISPLAY_WIDTH as i32,
         DISPLAY_HEIGHT as i32,
     ));
-    commands.insert_resource(CursorPos(None));
 
     spawn_player(&mut commands, Position(player_start));
-    for pos in rooms.iter().skip(1).map(Rect::center) {
-        spawn_monster(&mut commands, &mut rng, Position(pos));
-    }
+    log.add(
+        Color::YELLOW,
+        "Welcome to the dungeon, find the Amulet of Yala!",
+    );
 
     info!("initializing rusty_dungeon...done {:?}", start.elapsed());
 }
 
+/// Starts a new game with a new hero
+#[allow(clippy::too_many_arguments)]
+fn restart(
+    mut commands: Commands,
+    mut events: EventReader<RestartEvent>,
+    mut turn_state: ResMut<State<TurnState>>,
+    mut depth: ResMut<DungeonDepth>,
+    mut turns: ResMut<TurnCounter>,
+    mut camera: ResMut<Camera>,
+    architect: Res<ArchitectKind>,
+    corner_cutting: Res<CornerCutting>,
+    templates: Res<SpawnTemplates>,
+    mut log: ResMut<GameLog>,
+    mut rng: ResMut<GameRng>,
+    entities: Query<Entity, Or<(With<Position>, With<Carried>)>>,
+) {
+    // puffin::profile_function!();
+    if events.iter().count() == 0 {
+        return;
+    }
+    info!("Restarting...");
+    entities.for_each(|entity| commands.entity(entity).despawn());
+    log.clear();
+    log.add(Color::YELLOW, "A new hero enters the dungeon.");
+
+    depth.0 = 1;
+    turns.0 = 0;
+    let player_start = spawn_level(
+        &mut commands,
+        rng.rng(),
+        *architect,
+        *corner_cutting,
+        &templates,
+        depth.0,
+    );
+    spawn_player(&mut commands, Position(player_start));
+    camera.on_player_move(player_start.as_ivec2());
+
+    turn_state
+        .set(TurnState::AwaitingInput)
+        .expect("failed to set state");
+}
+
+/// Whether the screen is cleared and the dungeon drawn again this frame, see `RedrawPolicy`.
+/// The end screens draw everything themselves.
+fn needs_redraw(
+    policy: Res<RedrawPolicy>,
+    turn_state: Res<State<TurnState>>,
+    camera: Res<Camera>,
+    cursor_pos: Res<CursorPos>,
+    log: Res<GameLog>,
+    new_layers: Query<(), Added<Layer>>,
+    mut last_state: Local<Option<TurnState>>,
+) -> ShouldRun {
+    // the state driver changes the resource every frame, only the current state matters
+    let is_new_state = last_state.as_ref() != Some(turn_state.current());
+    if is_new_state {
+        *last_state = Some(turn_state.current().clone());
+    }
+    let has_changed = is_new_state
+        || camera.is_changed()
+        || cursor_pos.is_changed()
+        || log.is_changed()
+        || new_layers.iter().next().is_some();
+    let is_end_screen = matches!(
+        turn_state.current(),
+        TurnState::GameOver | TurnState::Victory
+    );
+    if !is_end_screen && (*policy == RedrawPolicy::EveryFrame || has_changed) {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
 fn clear_screen(mut ctx: DrawContext) {
     // puffin::profile_function!();
     ctx.cls_all_layers();
 }
 
-fn update_cursor(mut cursor_pos: ResMut<CursorPos>, windows: Res<Windows>) {
+/// Shows as much of the dungeon as the map layer holds, it changes when the window is resized
+fn fit_camera(mut ctx: DrawContext, mut camera: ResMut<Camera>) {
     // puffin::profile_function!();
-    cursor_pos.0 = windows
-        .get_primary()
-        .and_then(Window::cursor_position)
-        .map(|cursor_position| {
-            #[allow(clippy::cast_precision_loss)]
-            let mut pos = Vec2::new(
-                (cursor_position.x / (TILE_WIDTH as f32)).floor(),
-                (cursor_position.y / (TILE_HEIGHT as f32)).floor(),
-            )
-            .as_uvec2();
-
-            if pos.y > DISPLAY_HEIGHT - 1 {
-                pos.y = 0;
-            } else {
-                pos.y = DISPLAY_HEIGHT - 1 - pos.y;
-            }
-            pos
-        });
+    ctx.set_active_layer(LayerId::Map as u8);
+    let size = ctx.get_active_layer_size().as_ivec2();
+    if size.x != camera.display_width || size.y != camera.display_height {
+        camera.resize(size.x, size.y);
+    }
+}
+
+fn update_cursor(
+    mut cursor_pos: ResMut<CursorPos>,
+    windows: Res<Windows>,
+    layout: Res<TilemapLayout>,
+) {
+    // puffin::profile_function!();
+    let cursor_position = windows.get_primary().and_then(Window::cursor_position);
+    let tiles = CursorPos {
+        map: cursor_position.and_then(|position| layout.tile_at(position, LayerId::Map)),
+        hud: cursor_position.and_then(|position| layout.tile_at(position, LayerId::Hud)),
+    };
+    // only a move of the cursor asks for a redraw
+    if *cursor_pos != tiles {
+        *cursor_pos = tiles;
+    }
 }
 
-fn diagnostic(mut ctx: DrawContext, diagnostics: Res<Diagnostics>) {
+fn diagnostic(mut ctx: DrawContext, diagnostics: Res<Diagnostics>, rng: Res<GameRng>) {
     // puffin::profile_function!();
+    ctx.set_active_layer(LayerId::Diagnostic as u8);
     if let Some(fps) = diagnostics
         .get(FrameTimeDiagnosticsPlugin::FPS)
         .and_then(Diagnostic::value)
     {
-        ctx.set_active_layer(LayerId::Diagnostic as u8);
         ctx.print(0, 0, &format!("FPS {:.0}", fps));
     }
+    // the seed is needed to reproduce a bug
+    ctx.print(0, 1, &format!("Seed {}", rng.seed()));
 }
@@ -0,0 +1,242 @@
+use bevy::prelude::*;
+use std::{cmp::Reverse, collections::BinaryHeap, collections::VecDeque};
+
+use super::{components::Position, map::Map};
+
+/// Flow map of the distance from every reachable tile to the closest starting point.
+/// Following the lowest neighbour from anywhere on the map leads to a starting point.
+pub struct DijkstraMap {
+    width: u32,
+    distances: Vec<Option<u32>>,
+}
+
+impl DijkstraMap {
+    pub fn new(map: &Map, starts: &[UVec2]) -> Self {
+        let mut distances = vec![None; (map.width() * map.height()) as usize];
+        let mut open_list = VecDeque::new();
+        for start in starts {
+            if map.in_bounds(Position(*start)) {
+                distances[(start.y * map.width() + start.x) as usize] = Some(0);
+                open_list.push_back(*start);
+            }
+        }
+
+        // every step costs the same, so a breadth first search is enough to visit
+        // the tiles in order of distance
+        while let Some(point) = open_list.pop_front() {
+            let distance = distances[(point.y * map.width() + point.x) as usize]
+                .expect("open tiles always have a distance");
+            for exit in map.available_exits(point) {
+                let index = (exit.y * map.width() + exit.x) as usize;
+                if distances[index].is_none() {
+                    distances[index] = Some(distance + 1);
+                    open_list.push_back(exit);
+                }
+            }
+        }
+
+        Self {
+            width: map.width(),
+            distances,
+        }
+    }
+
+    /// Returns the distance to the closest starting point, or None if it can't be reached
+    pub fn get(&self, point: UVec2) -> Option<u32> {
+        if point.x >= self.width {
+            return None;
+        }
+        self.distances
+            .get((point.y * self.width + point.x) as usize)
+            .copied()
+            .flatten()
+    }
+
+    /// Returns the reachable point that is the farthest from the starting points
+    pub fn farthest_point(&self) -> Option<UVec2> {
+        self.distances
+            .iter()
+            .enumerate()
+            .filter_map(|(index, distance)| distance.map(|distance| (distance, index)))
+            .max_by_key(|(distance, _)| *distance)
+            .map(|(_, index)| {
+                #[allow(clippy::cast_possible_truncation)]
+                let index = index as u32;
+                UVec2::new(index % self.width, index / self.width)
+            })
+    }
+
+    /// Returns the neighbour of the point that is the closest to a starting point
+    pub fn find_lowest_exit(&self, map: &Map, point: UVec2) -> Option<UVec2> {
+        map.available_exits(point)
+            .into_iter()
+            .filter_map(|exit| self.get(exit).map(|distance| (distance, exit)))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, exit)| exit)
+    }
+}
+
+/// Number of steps between two points on an open map, diagonal steps cost the same
+fn distance(a: UVec2, b: UVec2) -> u32 {
+    let delta = (a.as_ivec2() - b.as_ivec2()).abs().as_uvec2();
+    delta.x.max(delta.y)
+}
+
+/// Finds the shortest path between two points with A*, over the tiles accepted by `is_allowed`.
+/// The returned path doesn't include the start but includes the end.
+pub fn a_star<F: Fn(UVec2) -> bool>(
+    map: &Map,
+    start: UVec2,
+    end: UVec2,
+    is_allowed: F,
+) -> Option<Vec<UVec2>> {
+    if !map.in_bounds(Position(start)) || !map.can_enter_tile(Position(end)) || !is_allowed(end) {
+        return None;
+    }
+    if start == end {
+        return Some(vec![]);
+    }
+
+    let index = |point: UVec2| (point.y * map.width() + point.x) as usize;
+    let mut came_from = vec![None; (map.width() * map.height()) as usize];
+    let mut cost_so_far = vec![u32::MAX; (map.width() * map.height()) as usize];
+    let mut open_list = BinaryHeap::new();
+
+    cost_so_far[index(start)] = 0;
+    open_list.push(Reverse((distance(start, end), start.x, start.y)));
+
+    while let Some(Reverse((_, x, y))) = open_list.pop() {
+        let point = UVec2::new(x, y);
+        if point == end {
+            let mut path = vec![end];
+            let mut current = end;
+            while let Some(previous) = came_from[index(current)] {
+                if previous == start {
+                    break;
+                }
+                path.push(previous);
+                current = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let cost = cost_so_far[index(point)] + 1;
+        for exit in map.available_exits(point) {
+            if is_allowed(exit) && cost < cost_so_far[index(exit)] {
+                cost_so_far[index(exit)] = cost;
+                came_from[index(exit)] = Some(point);
+                open_list.push(Reverse((cost + distance(exit, end), exit.x, exit.y)));
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_dungeon_plugin::map::{CornerCutting, TileType};
+
+    /// 7x5 room with a wall in the middle that has an opening at the bottom
+    fn test_map() -> Map {
+        let mut map = Map::new(7, 5);
+        for y in 0..4 {
+            map.set_tile(Position(UVec2::new(3, y)), TileType::Wall);
+        }
+        map
+    }
+
+    #[test]
+    fn dijkstra_distances() {
+        let map = test_map();
+        let dijkstra_map = DijkstraMap::new(&map, &[UVec2::new(0, 0)]);
+        assert_eq!(dijkstra_map.get(UVec2::new(0, 0)), Some(0));
+        assert_eq!(dijkstra_map.get(UVec2::new(2, 0)), Some(2));
+        assert_eq!(dijkstra_map.get(UVec2::new(3, 0)), None);
+        // around the wall, without cutting its corners
+        assert_eq!(dijkstra_map.get(UVec2::new(4, 0)), Some(10));
+    }
+
+    #[test]
+    fn dijkstra_corner_cutting() {
+        let mut map = test_map();
+        map.set_corner_cutting(CornerCutting::Allow);
+        let dijkstra_map = DijkstraMap::new(&map, &[UVec2::new(0, 0)]);
+        assert_eq!(dijkstra_map.get(UVec2::new(3, 4)), Some(4));
+        assert_eq!(dijkstra_map.get(UVec2::new(4, 0)), Some(8));
+    }
+
+    #[test]
+    fn dijkstra_unreachable() {
+        let mut map = test_map();
+        map.set_tile(Position(UVec2::new(3, 4)), TileType::Wall);
+        let dijkstra_map = DijkstraMap::new(&map, &[UVec2::new(0, 0)]);
+        assert_eq!(dijkstra_map.get(UVec2::new(6, 4)), None);
+        assert_eq!(dijkstra_map.find_lowest_exit(&map, UVec2::new(6, 4)), None);
+    }
+
+    #[test]
+    fn dijkstra_farthest_point() {
+        let map = test_map();
+        let dijkstra_map = DijkstraMap::new(&map, &[UVec2::new(0, 0)]);
+        assert_eq!(dijkstra_map.farthest_point(), Some(UVec2::new(6, 0)));
+    }
+
+    #[test]
+    fn dijkstra_lowest_exit() {
+        let map = test_map();
+        let dijkstra_map = DijkstraMap::new(&map, &[UVec2::new(0, 0)]);
+        assert_eq!(
+            dijkstra_map.find_lowest_exit(&map, UVec2::new(4, 0)),
+            Some(UVec2::new(4, 1))
+        );
+        assert_eq!(
+            dijkstra_map.find_lowest_exit(&map, UVec2::new(1, 0)),
+            Some(UVec2::new(0, 0))
+        );
+    }
+
+    #[test]
+    fn a_star_path() {
+        let map = test_map();
+        let path =
+            a_star(&map, UVec2::new(2, 0), UVec2::new(4, 0), |_| true).expect("path not found");
+        assert_eq!(path.len(), 10);
+        assert_eq!(path.first(), Some(&UVec2::new(2, 1)));
+        assert_eq!(path.last(), Some(&UVec2::new(4, 0)));
+        assert!(path
+            .iter()
+            .all(|point| map.can_enter_tile(Position(*point))));
+    }
+
+    #[test]
+    fn a_star_diagonal_path() {
+        let map = test_map();
+        let path =
+            a_star(&map, UVec2::new(0, 0), UVec2::new(2, 2), |_| true).expect("path not found");
+        assert_eq!(path, vec![UVec2::new(1, 1), UVec2::new(2, 2)]);
+    }
+
+    #[test]
+    fn a_star_no_path() {
+        let mut map = test_map();
+        map.set_tile(Position(UVec2::new(3, 4)), TileType::Wall);
+        assert!(a_star(&map, UVec2::new(0, 0), UVec2::new(6, 0), |_| true).is_none());
+        assert!(a_star(&map, UVec2::new(0, 0), UVec2::new(3, 0), |_| true).is_none());
+    }
+
+    #[test]
+    fn a_star_allowed_tiles() {
+        let map = test_map();
+        // the opening in the wall is left out
+        let is_allowed = |point: UVec2| point != UVec2::new(3, 4);
+        assert!(a_star(&map, UVec2::new(2, 0), UVec2::new(4, 0), is_allowed).is_none());
+        assert!(a_star(&map, UVec2::new(0, 0), UVec2::new(3, 4), is_allowed).is_none());
+        assert_eq!(
+            a_star(&map, UVec2::new(0, 0), UVec2::new(2, 2), is_allowed),
+            Some(vec![UVec2::new(1, 1), UVec2::new(2, 2)])
+        );
+    }
+}
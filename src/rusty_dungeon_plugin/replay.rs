@@ -0,0 +1,260 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{ensure, Context, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    map::{CornerCutting, Map},
+    RestartEvent,
+};
+use crate::game_rng::GameRng;
+
+/// Bumped every time the format or the numbers drawn from a seed change,
+/// older recordings are rejected
+const RECORDING_VERSION: u32 = 3;
+
+/// What the player does in a turn, sent by `keyboard_actions` or `play_back`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerAction {
+    Move {
+        dx: i32,
+        dy: i32,
+    },
+    /// Skips the turn to heal a little
+    Wait,
+    PickUp,
+    /// Uses the item at this index of the inventory
+    UseItem(usize),
+    /// Takes the stairs down when standing on them
+    Descend,
+}
+
+/// The actions of a game, replaying them from the seed with the same rules produces the same game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recording {
+    pub version: u32,
+    pub seed: u64,
+    pub corner_cutting: CornerCutting,
+    pub actions: Vec<PlayerAction>,
+}
+
+impl Recording {
+    pub fn new(seed: u64, corner_cutting: CornerCutting, actions: Vec<PlayerAction>) -> Self {
+        Self {
+            version: RECORDING_VERSION,
+            seed,
+            corner_cutting,
+            actions,
+        }
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .context("failed to serialize the recording")
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self> {
+        let recording: Self = ron::from_str(text).context("failed to parse the recording")?;
+        ensure!(
+            recording.version == RECORDING_VERSION,
+            "unsupported recording version {}, expected {}",
+            recording.version,
+            RECORDING_VERSION
+        );
+        Ok(recording)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_ron()?)
+            .with_context(|| format!("failed to write the recording to {}", path.display()))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read the recording from {}", path.display()))?;
+        Self::from_ron(&text)
+            .with_context(|| format!("failed to load the recording from {}", path.display()))
+    }
+}
+
+/// Keeps the actions of the player, they are written to the file after every turn
+/// so the recording survives a crash
+#[derive(Debug, Default)]
+pub struct Recorder {
+    path: Option<PathBuf>,
+    actions: Vec<PlayerAction>,
+    /// A restarted game doesn't start from the seed, it can't be replayed
+    is_stopped: bool,
+}
+
+impl Recorder {
+    pub fn to_file<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn recording(&self, seed: u64, corner_cutting: CornerCutting) -> Recording {
+        Recording::new(seed, corner_cutting, self.actions.clone())
+    }
+}
+
+/// Sends the actions of a recording, one per turn
+#[derive(Debug, Default)]
+pub struct Playback {
+    actions: VecDeque<PlayerAction>,
+    /// Frames to wait between two actions so a replay can be watched
+    frames_per_action: u32,
+    frames_left: u32,
+}
+
+impl Playback {
+    pub fn new(actions: Vec<PlayerAction>, frames_per_action: u32) -> Self {
+        Self {
+            actions: actions.into(),
+            frames_per_action,
+            frames_left: frames_per_action,
+        }
+    }
+
+    /// The keyboard is ignored until the playback is finished
+    pub fn is_finished(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+pub fn play_back(mut playback: ResMut<Playback>, mut actions: EventWriter<PlayerAction>) {
+    // puffin::profile_function!();
+    if playback.is_finished() {
+        return;
+    }
+    if playback.frames_left > 0 {
+        playback.frames_left -= 1;
+        return;
+    }
+    playback.frames_left = playback.frames_per_action;
+    if let Some(action) = playback.actions.pop_front() {
+        actions.send(action);
+    }
+}
+
+pub fn record_actions(
+    mut recorder: ResMut<Recorder>,
+    mut actions: EventReader<PlayerAction>,
+    mut restart_events: EventReader<RestartEvent>,
+    rng: Res<GameRng>,
+    map: Res<Map>,
+) {
+    // puffin::profile_function!();
+    if recorder.is_stopped {
+        return;
+    }
+    if restart_events.iter().count() > 0 {
+        info!("The game was restarted, recording stopped");
+        recorder.is_stopped = true;
+        return;
+    }
+    let count = recorder.actions.len();
+    recorder.actions.extend(actions.iter());
+    if recorder.actions.len() == count {
+        return;
+    }
+    if let Some(path) = &recorder.path {
+        if let Err(e) = recorder
+            .recording(rng.seed(), map.corner_cutting())
+            .save(path)
+        {
+            warn!("Failed to save the recording: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_dungeon_plugin::{
+        headless::{headless_app, run_headless, ScriptedInput},
+        save::SaveGame,
+    };
+    use ntest::timeout;
+
+    #[test]
+    fn recording_round_trip() {
+        let recording = Recording::new(
+            42,
+            CornerCutting::Allow,
+            vec![
+                PlayerAction::Move { dx: 1, dy: 0 },
+                PlayerAction::Wait,
+                PlayerAction::PickUp,
+                PlayerAction::UseItem(2),
+                PlayerAction::Descend,
+            ],
+        );
+        let text = recording.to_ron().expect("failed to serialize");
+        let loaded = Recording::from_ron(&text).expect("failed to parse");
+        assert_eq!(loaded, recording);
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let text = Recording::new(42, CornerCutting::Forbid, vec![])
+            .to_ron()
+            .expect("failed to serialize")
+            .replace(
+                &format!("version: {}", RECORDING_VERSION),
+                &format!("version: {}", RECORDING_VERSION + 1),
+            );
+        assert!(Recording::from_ron(&text).is_err());
+    }
+
+    /// Fails when a system does something that doesn't only depend on the seed and the actions
+    #[test]
+    #[timeout(20000)]
+    fn replay_reproduces_the_game() {
+        let mut recorded = headless_app(GameRng::new(42), ScriptedInput::random(7));
+        let summary = run_headless(&mut recorded, 50);
+        let recording = recorded
+            .world
+            .get_resource::<Recorder>()
+            .expect("no recorder")
+            .recording(42, CornerCutting::default());
+        assert!(!recording.actions.is_empty());
+
+        let mut replayed = headless_app(GameRng::new(recording.seed), ScriptedInput::default());
+        replayed.insert_resource(Playback::new(recording.actions, 0));
+        let replayed_summary = run_headless(&mut replayed, summary.turns);
+
+        assert_eq!(replayed_summary, summary);
+        let expected = SaveGame::capture(&mut recorded.world).expect("failed to capture");
+        let actual = SaveGame::capture(&mut replayed.world).expect("failed to capture");
+        assert_eq!(actual, expected);
+    }
+
+    /// Saving is not an action, a recording with saves in the middle still replays
+    #[test]
+    #[timeout(20000)]
+    fn saving_keeps_the_replay() {
+        let mut recorded = headless_app(GameRng::new(42), ScriptedInput::random(7));
+        run_headless(&mut recorded, 20);
+        SaveGame::capture(&mut recorded.world).expect("failed to capture");
+        let summary = run_headless(&mut recorded, 50);
+        let recording = recorded
+            .world
+            .get_resource::<Recorder>()
+            .expect("no recorder")
+            .recording(42, CornerCutting::default());
+
+        let mut replayed = headless_app(GameRng::new(recording.seed), ScriptedInput::default());
+        replayed.insert_resource(Playback::new(recording.actions, 0));
+        assert_eq!(run_headless(&mut replayed, summary.turns), summary);
+    }
+}
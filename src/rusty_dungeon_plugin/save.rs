@@ -0,0 +1,563 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, ensure, Context, Result};
+use bevy::{ecs::world::EntityMut, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    camera::Camera,
+    components::{
+        AmuletOfYala, Armor, Carried, ChasingPlayer, Damage, Enemy, FieldOfView, Health, Item,
+        MovingRandomly, Name, Player, Position, ProvidesDungeonMap, ProvidesHealing, Render,
+        Weapon,
+    },
+    game_log::GameLog,
+    map::{CornerCutting, Map, TileType},
+    DungeonDepth, TurnState,
+};
+use crate::ascii_tilemap_plugin::color::GlyphColor;
+use crate::game_rng::GameRng;
+use crate::key_bindings::{Action, KeyBindings};
+use crate::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+/// Where the game is saved, relative to the working directory
+pub const SAVE_PATH: &str = "savegame.ron";
+/// Bumped every time the format or the numbers drawn from a seed change,
+/// older saves are rejected
+const SAVE_VERSION: u32 = 3;
+
+/// Set when the saved game should be loaded on startup
+pub struct ContinueGame(pub bool);
+
+/// Components without data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tag {
+    Player,
+    Enemy,
+    Item,
+    Weapon,
+    AmuletOfYala,
+    MovingRandomly,
+    ChasingPlayer,
+    ProvidesDungeonMap,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenderData {
+    pub glyph: char,
+    pub background: [f32; 4],
+    pub foreground: [f32; 4],
+}
+
+/// A gameplay entity, entities refer to each other by their index in `SaveGame::entities`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EntityData {
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub position: Option<(u32, u32)>,
+    #[serde(default)]
+    pub health: Option<(i32, i32)>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub render: Option<RenderData>,
+    #[serde(default)]
+    pub damage: Option<i32>,
+    #[serde(default)]
+    pub defense: Option<i32>,
+    #[serde(default)]
+    pub healing: Option<i32>,
+    #[serde(default)]
+    pub carried_by: Option<usize>,
+    #[serde(default)]
+    pub fov_radius: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapData {
+    pub width: u32,
+    pub height: u32,
+    pub tiles: Vec<TileType>,
+    pub revealed: Vec<bool>,
+    #[serde(default)]
+    pub corner_cutting: CornerCutting,
+}
+
+/// Everything needed to continue a game.
+/// Games are only saved while awaiting input so the turn state isn't part of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub version: u32,
+    pub seed: u64,
+    pub rng_state: u64,
+    pub depth: u32,
+    pub map: MapData,
+    pub entities: Vec<EntityData>,
+}
+
+impl MapData {
+    fn capture(map: &Map) -> Self {
+        let points = (0..map.height())
+            .flat_map(|y| (0..map.width()).map(move |x| Position(UVec2::new(x, y))))
+            .collect::<Vec<_>>();
+        Self {
+            width: map.width(),
+            height: map.height(),
+            tiles: points
+                .iter()
+                .map(|point| map.get_tile(*point).unwrap_or(TileType::Wall))
+                .collect(),
+            revealed: points.iter().map(|point| map.is_revealed(*point)).collect(),
+            corner_cutting: map.corner_cutting(),
+        }
+    }
+
+    fn to_map(&self) -> Result<Map> {
+        let len = (self.width * self.height) as usize;
+        ensure!(
+            self.tiles.len() == len && self.revealed.len() == len,
+            "the map has {} tiles and {} revealed tiles instead of {}",
+            self.tiles.len(),
+            self.revealed.len(),
+            len
+        );
+        let mut map = Map::new(self.width, self.height);
+        map.set_corner_cutting(self.corner_cutting);
+        for (i, (tile, revealed)) in self.tiles.iter().zip(&self.revealed).enumerate() {
+            #[allow(clippy::cast_possible_truncation)]
+            let position = Position(UVec2::new(i as u32 % self.width, i as u32 / self.width));
+            map.set_tile(position, *tile);
+            if *revealed {
+                map.reveal(position);
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl SaveGame {
+    /// Captures the current game, it goes on as if it wasn't saved
+    pub fn capture(world: &mut World) -> Result<Self> {
+        let mut entities = world
+            .query_filtered::<Entity, Or<(With<Position>, With<Carried>)>>()
+            .iter(world)
+            .collect::<Vec<_>>();
+        entities.sort();
+
+        let mut entity_data = entities
+            .iter()
+            .map(|entity| capture_entity(world, *entity))
+            .collect::<Vec<_>>();
+        for (i, entity) in entities.iter().enumerate() {
+            if let Some(Carried(owner)) = world.get::<Carried>(*entity) {
+                entity_data[i].carried_by = entities.iter().position(|e| e == owner);
+            }
+        }
+
+        let map = MapData::capture(world.get_resource::<Map>().context("no map")?);
+        let depth = world
+            .get_resource::<DungeonDepth>()
+            .context("no dungeon depth")?
+            .0;
+        let rng = world
+            .get_resource::<GameRng>()
+            .context("no random number generator")?;
+        Ok(Self {
+            version: SAVE_VERSION,
+            seed: rng.seed(),
+            rng_state: rng.state(),
+            depth,
+            map,
+            entities: entity_data,
+        })
+    }
+
+    /// Replaces the current game with the saved one.
+    /// Nothing is changed when the save is invalid.
+    pub fn restore(&self, world: &mut World) -> Result<()> {
+        ensure!(
+            self.version == SAVE_VERSION,
+            "unsupported save version {}, expected {}",
+            self.version,
+            SAVE_VERSION
+        );
+        let map = self.map.to_map()?;
+        for (i, data) in self.entities.iter().enumerate() {
+            if let Some(owner) = data.carried_by {
+                ensure!(
+                    owner < self.entities.len(),
+                    "entity #{} is carried by unknown entity #{}",
+                    i,
+                    owner
+                );
+            }
+        }
+        let player_position = match self
+            .entities
+            .iter()
+            .filter(|data| data.tags.contains(&Tag::Player))
+            .map(|data| data.position)
+            .collect::<Vec<_>>()[..]
+        {
+            [Some((x, y))] => UVec2::new(x, y),
+            [None] => bail!("the player has no position"),
+            _ => bail!("the save must contain exactly one player"),
+        };
+
+        let old_entities = world
+            .query_filtered::<Entity, Or<(With<Position>, With<Carried>)>>()
+            .iter(world)
+            .collect::<Vec<_>>();
+        for entity in old_entities {
+            world.despawn(entity);
+        }
+
+        let entities = self
+            .entities
+            .iter()
+            .map(|_| world.spawn().id())
+            .collect::<Vec<_>>();
+        for (data, entity) in self.entities.iter().zip(&entities) {
+            restore_entity(&mut world.entity_mut(*entity), data);
+            if let Some(owner) = data.carried_by {
+                world.entity_mut(*entity).insert(Carried(entities[owner]));
+            }
+        }
+
+        world.insert_resource(map);
+        world.insert_resource(DungeonDepth(self.depth));
+        world.insert_resource(GameRng::from_state(self.seed, self.rng_state));
+        #[allow(clippy::cast_possible_wrap)]
+        world.insert_resource(Camera::new(
+            player_position.as_ivec2(),
+            DISPLAY_WIDTH as i32,
+            DISPLAY_HEIGHT as i32,
+        ));
+        Ok(())
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .context("failed to serialize the game")
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self> {
+        ron::from_str(text).context("failed to parse the saved game")
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_ron()?)
+            .with_context(|| format!("failed to write the game to {}", path.display()))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read the game from {}", path.display()))?;
+        Self::from_ron(&text)
+            .with_context(|| format!("failed to load the game from {}", path.display()))
+    }
+}
+
+fn capture_entity(world: &World, entity: Entity) -> EntityData {
+    let mut tags = Vec::new();
+    let mut tag_if = |is_present: bool, tag: Tag| {
+        if is_present {
+            tags.push(tag);
+        }
+    };
+    tag_if(world.get::<Player>(entity).is_some(), Tag::Player);
+    tag_if(world.get::<Enemy>(entity).is_some(), Tag::Enemy);
+    tag_if(world.get::<Item>(entity).is_some(), Tag::Item);
+    tag_if(world.get::<Weapon>(entity).is_some(), Tag::Weapon);
+    tag_if(
+        world.get::<AmuletOfYala>(entity).is_some(),
+        Tag::AmuletOfYala,
+    );
+    tag_if(
+        world.get::<MovingRandomly>(entity).is_some(),
+        Tag::MovingRandomly,
+    );
+    tag_if(
+        world.get::<ChasingPlayer>(entity).is_some(),
+        Tag::ChasingPlayer,
+    );
+    tag_if(
+        world.get::<ProvidesDungeonMap>(entity).is_some(),
+        Tag::ProvidesDungeonMap,
+    );
+
+    EntityData {
+        tags,
+        position: world.get::<Position>(entity).map(|pos| (pos.0.x, pos.0.y)),
+        health: world
+            .get::<Health>(entity)
+            .map(|health| (health.current, health.max)),
+        name: world.get::<Name>(entity).map(|name| name.0.clone()),
+        render: world.get::<Render>(entity).map(|render| RenderData {
+            glyph: render.glyph,
+            background: render.color.background.as_rgba_f32(),
+            foreground: render.color.foreground.as_rgba_f32(),
+        }),
+        damage: world.get::<Damage>(entity).map(|damage| damage.0),
+        defense: world.get::<Armor>(entity).map(|armor| armor.defense),
+        healing: world
+            .get::<ProvidesHealing>(entity)
+            .map(|healing| healing.amount),
+        carried_by: None,
+        fov_radius: world.get::<FieldOfView>(entity).map(|fov| fov.radius),
+    }
+}
+
+fn restore_entity(entity: &mut EntityMut, data: &EntityData) {
+    for tag in &data.tags {
+        match tag {
+            Tag::Player => entity.insert(Player),
+            Tag::Enemy => entity.insert(Enemy),
+            Tag::Item => entity.insert(Item),
+            Tag::Weapon => entity.insert(Weapon),
+            Tag::AmuletOfYala => entity.insert(AmuletOfYala),
+            Tag::MovingRandomly => entity.insert(MovingRandomly),
+            Tag::ChasingPlayer => entity.insert(ChasingPlayer),
+            Tag::ProvidesDungeonMap => entity.insert(ProvidesDungeonMap),
+        };
+    }
+    if let Some((x, y)) = data.position {
+        entity.insert(Position(UVec2::new(x, y)));
+    }
+    if let Some((current, max)) = data.health {
+        entity.insert(Health { current, max });
+    }
+    if let Some(name) = &data.name {
+        entity.insert(Name(name.clone()));
+    }
+    if let Some(render) = &data.render {
+        let [r, g, b, a] = render.background;
+        let background = Color::rgba(r, g, b, a);
+        let [r, g, b, a] = render.foreground;
+        let foreground = Color::rgba(r, g, b, a);
+        entity.insert(Render {
+            color: GlyphColor::new(background, foreground),
+            glyph: render.glyph,
+        });
+    }
+    if let Some(damage) = data.damage {
+        entity.insert(Damage(damage));
+    }
+    if let Some(defense) = data.defense {
+        entity.insert(Armor { defense });
+    }
+    if let Some(amount) = data.healing {
+        entity.insert(ProvidesHealing { amount });
+    }
+    if let Some(radius) = data.fov_radius {
+        entity.insert(FieldOfView::new(radius));
+    }
+}
+
+/// Saves the game when the save key is pressed
+pub fn save_game(world: &mut World) {
+    // puffin::profile_function!();
+    let is_save_pressed = match (
+        world.get_resource::<KeyBindings>(),
+        world.get_resource::<Input<KeyCode>>(),
+    ) {
+        (Some(bindings), Some(input)) => bindings.just_pressed(input, Action::Save),
+        _ => false,
+    };
+    let is_awaiting_input = world
+        .get_resource::<State<TurnState>>()
+        .is_some_and(|state| *state.current() == TurnState::AwaitingInput);
+    if !is_save_pressed || !is_awaiting_input {
+        return;
+    }
+
+    let result = SaveGame::capture(world).and_then(|save| save.save(SAVE_PATH));
+    let message = match result {
+        Ok(()) => (Color::GREEN, "Game saved.".to_string()),
+        Err(e) => {
+            warn!("Failed to save the game: {:?}", e);
+            (Color::RED, format!("Failed to save the game: {}", e))
+        }
+    };
+    if let Some(mut log) = world.get_resource_mut::<GameLog>() {
+        log.add(message.0, message.1);
+    }
+}
+
+/// Replaces the new game with the saved one when the game was started with `--continue`
+pub fn continue_game(world: &mut World) {
+    if !world
+        .get_resource::<ContinueGame>()
+        .is_some_and(|continue_game| continue_game.0)
+    {
+        return;
+    }
+
+    let result = SaveGame::load(SAVE_PATH).and_then(|save| save.restore(world));
+    let message = match result {
+        Ok(()) => (Color::YELLOW, "Welcome back to the dungeon!".to_string()),
+        Err(e) => {
+            warn!("Failed to continue the game: {:?}", e);
+            (
+                Color::RED,
+                "Failed to load the saved game, starting a new one.".to_string(),
+            )
+        }
+    };
+    if let Some(mut log) = world.get_resource_mut::<GameLog>() {
+        log.clear();
+        log.add(message.0, message.1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_world() -> (World, Entity) {
+        let mut world = World::new();
+        let mut map = Map::new(10, 8);
+        map.set_tile(Position(UVec2::new(3, 3)), TileType::Wall);
+        map.set_tile(Position(UVec2::new(9, 7)), TileType::Exit);
+        map.reveal(Position(UVec2::new(1, 1)));
+        world.insert_resource(map);
+        world.insert_resource(DungeonDepth(2));
+        world.insert_resource(GameRng::new(1234));
+
+        let player = world
+            .spawn()
+            .insert(Player)
+            .insert(Position(UVec2::new(1, 1)))
+            .insert(Health {
+                current: 12,
+                max: 20,
+            })
+            .insert(Render {
+                color: GlyphColor::default(),
+                glyph: '@',
+            })
+            .insert(Damage(1))
+            .insert(FieldOfView::new(8))
+            .id();
+        world
+            .spawn()
+            .insert(Enemy)
+            .insert(ChasingPlayer)
+            .insert(Position(UVec2::new(5, 5)))
+            .insert(Name("Orc".to_string()))
+            .insert(Health { current: 2, max: 2 })
+            .insert(Damage(2))
+            .insert(FieldOfView::new(6));
+        world
+            .spawn()
+            .insert(Item)
+            .insert(Carried(player))
+            .insert(Name("Healing Potion".to_string()))
+            .insert(ProvidesHealing { amount: 6 });
+        world
+            .spawn()
+            .insert(Item)
+            .insert(Weapon)
+            .insert(Position(UVec2::new(2, 6)))
+            .insert(Name("Rusty Sword".to_string()))
+            .insert(Damage(1))
+            .insert(Render {
+                color: GlyphColor::foreground(Color::rgb(0.5, 0.25, 1.0)),
+                glyph: 's',
+            });
+        (world, player)
+    }
+
+    #[test]
+    fn round_trip_reproduces_world() {
+        let (mut world, _) = test_world();
+        let save = SaveGame::capture(&mut world).expect("failed to capture");
+        let text = save.to_ron().expect("failed to serialize");
+        let loaded = SaveGame::from_ron(&text).expect("failed to parse");
+        assert_eq!(loaded, save);
+
+        let mut restored = World::new();
+        loaded.restore(&mut restored).expect("failed to restore");
+
+        // capturing again also compares the rng state of both worlds
+        let expected = SaveGame::capture(&mut world).expect("failed to capture");
+        let actual = SaveGame::capture(&mut restored).expect("failed to capture");
+        assert_eq!(actual, expected);
+        assert_eq!(actual.entities.len(), 4);
+        assert_eq!(actual.depth, 2);
+    }
+
+    #[test]
+    fn restore_replaces_current_game() {
+        let (mut world, _) = test_world();
+        let save = SaveGame::capture(&mut world).expect("failed to capture");
+
+        let (mut restored, _) = test_world();
+        restored.insert_resource(DungeonDepth(1));
+        restored.spawn().insert(Position(UVec2::ZERO));
+        save.restore(&mut restored).expect("failed to restore");
+
+        let count = restored
+            .query_filtered::<Entity, Or<(With<Position>, With<Carried>)>>()
+            .iter(&restored)
+            .count();
+        assert_eq!(count, 4);
+        assert_eq!(
+            restored.get_resource::<DungeonDepth>().map(|d| d.0),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn carried_items_stay_with_owner() {
+        let (mut world, _) = test_world();
+        let save = SaveGame::capture(&mut world).expect("failed to capture");
+        let mut restored = World::new();
+        save.restore(&mut restored).expect("failed to restore");
+
+        let player = restored
+            .query_filtered::<Entity, With<Player>>()
+            .iter(&restored)
+            .next()
+            .expect("no player");
+        let carried = restored
+            .query::<&Carried>()
+            .iter(&restored)
+            .map(|carried| carried.0)
+            .collect::<Vec<_>>();
+        assert_eq!(carried, vec![player]);
+    }
+
+    #[test]
+    fn invalid_save_changes_nothing() {
+        let (mut world, _) = test_world();
+        let save = SaveGame::capture(&mut world).expect("failed to capture");
+
+        let mut invalid = save.clone();
+        invalid.entities[2].carried_by = Some(100);
+        assert!(invalid.restore(&mut world).is_err());
+
+        let mut invalid = save.clone();
+        invalid.map.tiles.pop();
+        assert!(invalid.restore(&mut world).is_err());
+
+        let mut invalid = save.clone();
+        invalid
+            .entities
+            .retain(|data| !data.tags.contains(&Tag::Player));
+        assert!(invalid.restore(&mut world).is_err());
+
+        let mut invalid = save;
+        invalid.version += 1;
+        assert!(invalid.restore(&mut world).is_err());
+
+        let count = world
+            .query_filtered::<Entity, Or<(With<Position>, With<Carried>)>>()
+            .iter(&world)
+            .count();
+        assert_eq!(count, 4);
+    }
+}
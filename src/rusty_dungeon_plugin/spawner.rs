@@ -1,8 +1,52 @@
 use bevy::prelude::*;
 use fastrand::Rng;
 
-use super::components::{Enemy, Health, MovingRandomly, Name, Player, Position, Render};
+use super::{
+    components::{
+        AmuletOfYala, Armor, ChasingPlayer, Damage, Enemy, FieldOfView, Health, Item,
+        MovingRandomly, Name, Player, Position, ProvidesDungeonMap, ProvidesHealing, Render,
+        Weapon,
+    },
+    map::{CornerCutting, TileType},
+    map_builder::{ArchitectKind, MapBuilder, MapBuilderResult},
+    templates::{Ai, Effect, EntityType, SpawnTemplates, Template},
+    FINAL_DEPTH,
+};
 use crate::ascii_tilemap_plugin::color::GlyphColor;
+use crate::{HEIGHT, WIDTH};
+
+/// Builds the map of the given depth and spawns everything on it except the player.
+/// Returns the player start.
+pub fn spawn_level(
+    commands: &mut Commands,
+    rng: &mut Rng,
+    architect: ArchitectKind,
+    corner_cutting: CornerCutting,
+    templates: &SpawnTemplates,
+    depth: u32,
+) -> UVec2 {
+    let MapBuilderResult {
+        mut map,
+        player_start,
+        spawn_points,
+        exit,
+    } = MapBuilder::new(WIDTH, HEIGHT, architect, rng)
+        .corner_cutting(corner_cutting)
+        .build()
+        .expect("failed to build the map");
+
+    if depth == FINAL_DEPTH {
+        spawn_amulet_of_yala(commands, Position(exit));
+    } else {
+        map.set_tile(Position(exit), TileType::Exit);
+    }
+    for pos in spawn_points {
+        spawn_entity(commands, rng, templates, depth, Position(pos));
+    }
+    commands.insert_resource(map);
+
+    player_start
+}
 
 pub fn spawn_player(commands: &mut Commands, position: Position) {
     commands
@@ -16,35 +60,110 @@ pub fn spawn_player(commands: &mut Commands, position: Position) {
         .insert(Render {
             color: GlyphColor::default(),
             glyph: '@',
-        });
+        })
+        .insert(Damage(1))
+        .insert(FieldOfView::new(8));
 }
 
-pub fn spawn_monster(commands: &mut Commands, rng: &mut Rng, position: Position) {
-    let (hp, name, glyph) = match rng.u32(1..10) {
-        1..=8 => goblin(),
-        _ => orc(),
-    };
+/// Spawns a monster or an item picked from the templates available at this depth
+pub fn spawn_entity(
+    commands: &mut Commands,
+    rng: &mut Rng,
+    templates: &SpawnTemplates,
+    depth: u32,
+    position: Position,
+) {
+    match templates.choose(rng, depth) {
+        Some(template) => match template.entity_type {
+            EntityType::Enemy => spawn_monster(commands, template, position),
+            EntityType::Item => spawn_item(commands, template, position),
+            EntityType::Weapon => spawn_weapon(commands, template, position),
+            EntityType::Armor => spawn_armor(commands, template, position),
+        },
+        None => warn!("No spawn template available at depth {}", depth),
+    }
+}
 
-    commands
-        .spawn()
+pub fn spawn_monster(commands: &mut Commands, template: &Template, position: Position) {
+    let hp = template.hp.unwrap_or(1);
+    let mut monster = commands.spawn();
+    monster
         .insert(Enemy)
         .insert(position)
-        .insert(MovingRandomly)
-        .insert(Name(name))
+        .insert(Name(template.name.clone()))
         .insert(Health {
             current: hp,
             max: hp,
         })
         .insert(Render {
-            color: GlyphColor::foreground(Color::WHITE),
-            glyph,
+            color: GlyphColor::foreground(template.color()),
+            glyph: template.glyph,
+        })
+        .insert(Damage(template.damage.unwrap_or(1)))
+        .insert(FieldOfView::new(6));
+    match template.ai.unwrap_or(Ai::MovingRandomly) {
+        Ai::MovingRandomly => monster.insert(MovingRandomly),
+        Ai::ChasingPlayer => monster.insert(ChasingPlayer),
+    };
+}
+
+pub fn spawn_item(commands: &mut Commands, template: &Template, position: Position) {
+    let mut item = commands.spawn();
+    item.insert(Item)
+        .insert(position)
+        .insert(Name(template.name.clone()))
+        .insert(Render {
+            color: GlyphColor::foreground(template.color()),
+            glyph: template.glyph,
+        });
+    match template.effect {
+        Some(Effect::Healing(amount)) => {
+            item.insert(ProvidesHealing { amount });
+        }
+        Some(Effect::DungeonMap) => {
+            item.insert(ProvidesDungeonMap);
+        }
+        None => {}
+    }
+}
+
+pub fn spawn_weapon(commands: &mut Commands, template: &Template, position: Position) {
+    commands
+        .spawn()
+        .insert(Item)
+        .insert(Weapon)
+        .insert(position)
+        .insert(Name(template.name.clone()))
+        .insert(Damage(template.damage.unwrap_or(0)))
+        .insert(Render {
+            color: GlyphColor::foreground(template.color()),
+            glyph: template.glyph,
         });
 }
 
-fn goblin() -> (i32, String, char) {
-    (1, "Goblin".to_string(), 'g')
+pub fn spawn_armor(commands: &mut Commands, template: &Template, position: Position) {
+    commands
+        .spawn()
+        .insert(Item)
+        .insert(Armor {
+            defense: template.defense.unwrap_or(0),
+        })
+        .insert(position)
+        .insert(Name(template.name.clone()))
+        .insert(Render {
+            color: GlyphColor::foreground(template.color()),
+            glyph: template.glyph,
+        });
 }
 
-fn orc() -> (i32, String, char) {
-    (2, "Orc".to_string(), 'o')
+pub fn spawn_amulet_of_yala(commands: &mut Commands, position: Position) {
+    commands
+        .spawn()
+        .insert(AmuletOfYala)
+        .insert(position)
+        .insert(Name("Amulet of Yala".to_string()))
+        .insert(Render {
+            color: GlyphColor::foreground(Color::GOLD),
+            glyph: '|',
+        });
 }
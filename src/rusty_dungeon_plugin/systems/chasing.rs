@@ -0,0 +1,111 @@
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::rusty_dungeon_plugin::{
+    components::{
+        ChasingPlayer, FieldOfView, Health, Player, Position, WantsToAttack, WantsToMove,
+    },
+    map::Map,
+    pathfinding::DijkstraMap,
+};
+
+pub fn chasing(
+    mut commands: Commands,
+    movers: Query<(Entity, &Position, &FieldOfView), With<ChasingPlayer>>,
+    positions: Query<&Position, With<Health>>,
+    player_query: Query<(Entity, &Position), With<Player>>,
+    map: Res<Map>,
+) {
+    // puffin::profile_function!();
+    let (player, player_position) = match player_query.get_single() {
+        Ok(player) => player,
+        _ => return,
+    };
+    let dijkstra_map = DijkstraMap::new(&map, &[player_position.0]);
+    // tiles a chaser already steps on this turn
+    let mut claimed = HashSet::default();
+
+    movers.for_each(|(entity, position, fov)| {
+        if !fov.visible_tiles.contains(&player_position.0) {
+            return;
+        }
+        let destination = match dijkstra_map.find_lowest_exit(&map, position.0) {
+            Some(destination) => destination,
+            _ => return,
+        };
+
+        if destination == player_position.0 {
+            commands.spawn().insert(WantsToAttack {
+                attacker: entity,
+                victim: player,
+            });
+        } else if !positions.iter().any(|position| position.0 == destination)
+            && claimed.insert(destination)
+        {
+            commands.spawn().insert(WantsToMove {
+                entity,
+                destination: Position(destination),
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_dungeon_plugin::map::TileType;
+
+    fn spawn_chaser(world: &mut World, position: UVec2, player_position: UVec2) {
+        let mut fov = FieldOfView::new(8);
+        fov.visible_tiles.insert(player_position);
+        world
+            .spawn()
+            .insert(ChasingPlayer)
+            .insert(Position(position))
+            .insert(Health { current: 1, max: 1 })
+            .insert(fov);
+    }
+
+    #[test]
+    fn chasers_dont_share_a_destination() {
+        // both chasers can only get closer through the tile between them
+        //  ##@##
+        //  ##.##
+        //  #C.C#
+        let mut map = Map::new(5, 3);
+        for (x, y) in [
+            (0, 0),
+            (1, 0),
+            (3, 0),
+            (4, 0),
+            (0, 1),
+            (1, 1),
+            (3, 1),
+            (4, 1),
+        ] {
+            map.set_tile(Position(UVec2::new(x, y)), TileType::Wall);
+        }
+        map.set_tile(Position(UVec2::new(0, 2)), TileType::Wall);
+        map.set_tile(Position(UVec2::new(4, 2)), TileType::Wall);
+
+        let mut world = World::new();
+        world.insert_resource(map);
+        let player_position = UVec2::new(2, 0);
+        world
+            .spawn()
+            .insert(Player)
+            .insert(Position(player_position))
+            .insert(Health { current: 1, max: 1 });
+        spawn_chaser(&mut world, UVec2::new(1, 2), player_position);
+        spawn_chaser(&mut world, UVec2::new(3, 2), player_position);
+
+        let mut stage = SystemStage::parallel().with_system(chasing);
+        stage.run(&mut world);
+
+        let destinations = world
+            .query::<&WantsToMove>()
+            .iter(&world)
+            .map(|wants_to_move| wants_to_move.destination.0)
+            .collect::<Vec<_>>();
+        assert_eq!(destinations, vec![UVec2::new(2, 2)]);
+    }
+}
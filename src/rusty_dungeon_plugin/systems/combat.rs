@@ -1,26 +1,136 @@
 use bevy::prelude::*;
 
-use crate::rusty_dungeon_plugin::components::{Health, WantsToAttack};
+use crate::ascii_tilemap_plugin::rich_text::{parse, plain};
+use crate::rusty_dungeon_plugin::{
+    components::{Armor, Carried, Damage, Health, Name, Player, WantsToAttack},
+    game_log::GameLog,
+};
 
+/// A hit always deals at least this much damage, no matter the armor
+const MIN_DAMAGE: i32 = 1;
+
+/// Combat stats of a creature including its equipment
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CombatStats {
+    pub damage: i32,
+    pub defense: i32,
+}
+
+/// Computes the damage dealt by the attacker to the victim
+pub fn calculate_damage(attacker: CombatStats, victim: CombatStats) -> i32 {
+    i32::max(MIN_DAMAGE, attacker.damage - victim.defense)
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn combat(
     mut commands: Commands,
     attackers: Query<(Entity, &WantsToAttack)>,
     mut health: Query<&mut Health>,
+    damage_query: Query<&Damage, Without<Carried>>,
+    equipment_query: Query<(&Carried, Option<&Damage>, Option<&Armor>)>,
+    player_query: Query<(), With<Player>>,
+    name_query: Query<&Name>,
+    mut log: ResMut<GameLog>,
 ) {
     // puffin::profile_function!();
-    let victims = attackers
+    let combat_stats = |entity: Entity| {
+        let mut stats = CombatStats {
+            damage: damage_query.get(entity).map_or(0, |damage| damage.0),
+            defense: 0,
+        };
+        for (_, damage, armor) in equipment_query
+            .iter()
+            .filter(|(carried, _, _)| carried.0 == entity)
+        {
+            stats.damage += damage.map_or(0, |damage| damage.0);
+            stats.defense += armor.map_or(0, |armor| armor.defense);
+        }
+        stats
+    };
+
+    let name = |entity: Entity| {
+        if player_query.get(entity).is_ok() {
+            "you".to_string()
+        } else {
+            name_query.get(entity).map_or_else(
+                |_| "something".to_string(),
+                |name| format!("the {}", name.0),
+            )
+        }
+    };
+
+    let attacks = attackers
         .iter()
-        .map(|(entity, attack)| (entity, attack.victim))
+        .map(|(entity, attack)| (entity, attack.attacker, attack.victim))
         .collect::<Vec<_>>();
-    for (message, victim) in victims {
+    for (message, attacker, victim) in attacks {
+        let damage = calculate_damage(combat_stats(attacker), combat_stats(victim));
         if let Ok(mut health) = health.get_mut(victim) {
-            info!("Health before attack: {}", health.current);
-            health.current -= 1;
-            if health.current < 1 {
+            let is_player_hit = player_query.get(victim).is_ok();
+            let (color, verb) = match (player_query.get(attacker).is_ok(), is_player_hit) {
+                (true, _) => (Color::WHITE, "hit"),
+                (_, true) => (Color::RED, "hits"),
+                _ => (Color::GRAY, "hits"),
+            };
+            // only the damage is markup, names are kept as they are
+            let mut text = plain(&capitalize(&format!(
+                "{} {} {}",
+                name(attacker),
+                verb,
+                name(victim)
+            )));
+            text.extend(parse(&format!(" for #[yellow]{}#[] damage.", damage)));
+            log.add_rich(color, text);
+            health.current -= damage;
+            // a dead player is handled by end_turn
+            if health.current < 1 && !is_player_hit {
                 commands.entity(victim).despawn();
+                log.add(
+                    Color::ORANGE,
+                    capitalize(&format!("{} dies.", name(victim))),
+                );
             }
-            info!("Health after attack: {}", health.current);
         }
         commands.entity(message).despawn();
     }
 }
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(damage: i32, defense: i32) -> CombatStats {
+        CombatStats { damage, defense }
+    }
+
+    #[test]
+    fn capitalize_first_letter() {
+        assert_eq!(capitalize("the Orc dies."), "The Orc dies.");
+        assert_eq!(capitalize(""), "");
+    }
+
+    #[test]
+    fn damage_without_armor() {
+        assert_eq!(calculate_damage(stats(1, 0), stats(0, 0)), 1);
+        assert_eq!(calculate_damage(stats(4, 0), stats(2, 0)), 4);
+    }
+
+    #[test]
+    fn armor_mitigates_damage() {
+        assert_eq!(calculate_damage(stats(4, 0), stats(0, 1)), 3);
+        assert_eq!(calculate_damage(stats(4, 0), stats(0, 3)), 1);
+    }
+
+    #[test]
+    fn damage_is_never_below_minimum() {
+        assert_eq!(calculate_damage(stats(1, 0), stats(0, 5)), MIN_DAMAGE);
+        assert_eq!(calculate_damage(stats(0, 0), stats(0, 0)), MIN_DAMAGE);
+    }
+}
@@ -1,11 +1,34 @@
 use bevy::prelude::*;
 
-use crate::rusty_dungeon_plugin::TurnState;
+use crate::rusty_dungeon_plugin::{
+    components::{AmuletOfYala, Health, Player, Position},
+    TurnCounter, TurnState,
+};
 
-pub fn end_turn(mut turn_state: ResMut<State<TurnState>>) {
+pub fn end_turn(
+    mut turn_state: ResMut<State<TurnState>>,
+    player_query: Query<(&Position, &Health), With<Player>>,
+    amulet_query: Query<&Position, With<AmuletOfYala>>,
+    mut turns: ResMut<TurnCounter>,
+) {
     // puffin::profile_function!();
+    let (player_position, player_health) = match player_query.get_single() {
+        Ok(player) => player,
+        _ => return,
+    };
+    let has_amulet = amulet_query
+        .iter()
+        .any(|position| position == player_position);
+    if *turn_state.current() == TurnState::PlayerTurn {
+        turns.0 += 1;
+    }
     match turn_state.current() {
-        TurnState::AwaitingInput => return,
+        TurnState::AwaitingInput
+        | TurnState::NextLevel
+        | TurnState::GameOver
+        | TurnState::Victory => return,
+        _ if player_health.current < 1 => turn_state.set(TurnState::GameOver),
+        TurnState::PlayerTurn if has_amulet => turn_state.set(TurnState::Victory),
         TurnState::PlayerTurn => turn_state.set(TurnState::MonserTurn),
         TurnState::MonserTurn => turn_state.set(TurnState::AwaitingInput),
     }
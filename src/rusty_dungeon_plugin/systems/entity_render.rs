@@ -3,7 +3,7 @@ use crate::ascii_tilemap_plugin::DrawContext;
 use crate::{
     rusty_dungeon_plugin::{
         camera::Camera,
-        components::{Position, Render},
+        components::{FieldOfView, Player, Position, Render},
     },
     LayerId,
 };
@@ -13,11 +13,16 @@ pub fn entity_render(
     mut ctx: DrawContext,
     camera: Res<Camera>,
     query: Query<(&Position, &Render)>,
+    player_fov: Query<&FieldOfView, With<Player>>,
 ) {
     // puffin::profile_function!();
+    let player_fov = player_fov.single();
     ctx.set_active_layer(LayerId::Entities as u8);
     let offset = IVec2::new(camera.left_x, camera.top_y);
     query.for_each(|(position, render)| {
+        if !player_fov.visible_tiles.contains(&position.0) {
+            return;
+        }
         let draw_pos = ((*position).0.as_ivec2() - offset).as_uvec2();
         ctx.set(
             draw_pos.x,
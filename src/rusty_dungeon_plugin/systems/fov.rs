@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+
+use crate::rusty_dungeon_plugin::{
+    components::{FieldOfView, Player, Position},
+    fov::field_of_view,
+    map::Map,
+};
+
+pub fn fov(mut views: Query<(&Position, &mut FieldOfView, Option<&Player>)>, mut map: ResMut<Map>) {
+    // puffin::profile_function!();
+    views.for_each_mut(|(position, mut fov, player)| {
+        if !fov.is_dirty {
+            return;
+        }
+        fov.visible_tiles = field_of_view(position.0, fov.radius, &map);
+        fov.is_dirty = false;
+        if player.is_some() {
+            for tile in &fov.visible_tiles {
+                map.reveal(Position(*tile));
+            }
+        }
+    });
+}
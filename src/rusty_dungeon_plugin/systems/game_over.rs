@@ -0,0 +1,94 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::ascii_tilemap_plugin::{geometry::Rect, rich_text::TextAlign, DrawContext};
+use crate::key_bindings::{Action, KeyBindings};
+use crate::rusty_dungeon_plugin::RestartEvent;
+use crate::LayerId;
+
+pub fn game_over(
+    mut ctx: DrawContext,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    app_exit_events: EventWriter<AppExit>,
+    restart_events: EventWriter<RestartEvent>,
+) {
+    // puffin::profile_function!();
+    ctx.cls_all_layers();
+    ctx.set_active_layer(LayerId::Hud as u8);
+    draw_end_screen_frame(&mut ctx);
+    ctx.print_color_centered(2, Color::BLACK, Color::RED, "Your quest has ended.");
+    ctx.print_wrapped(
+        story_rect(&mut ctx),
+        TextAlign::Center,
+        "Slain by a monster, your hero's journey has come to a premature end. \
+         The Amulet of Yala remains unclaimed, and your home town is not saved.",
+    );
+    ctx.print_centered(8, "Don't worry, you can always try again with a new hero.");
+    print_end_screen_keys(&mut ctx, &bindings);
+
+    handle_end_screen_input(&keyboard_input, &bindings, app_exit_events, restart_events);
+}
+
+pub fn victory(
+    mut ctx: DrawContext,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    app_exit_events: EventWriter<AppExit>,
+    restart_events: EventWriter<RestartEvent>,
+) {
+    // puffin::profile_function!();
+    ctx.cls_all_layers();
+    ctx.set_active_layer(LayerId::Hud as u8);
+    draw_end_screen_frame(&mut ctx);
+    ctx.print_color_centered(2, Color::BLACK, Color::GREEN, "You have won!");
+    ctx.print_wrapped(
+        story_rect(&mut ctx),
+        TextAlign::Center,
+        "You put on the Amulet of Yala and feel its power course through your veins. \
+         Your town is saved, and you can return to your normal life.",
+    );
+    print_end_screen_keys(&mut ctx, &bindings);
+
+    handle_end_screen_input(&keyboard_input, &bindings, app_exit_events, restart_events);
+}
+
+fn draw_end_screen_frame(ctx: &mut DrawContext) {
+    let width = ctx.get_active_layer_size().x;
+    ctx.draw_box_double(
+        Rect::with_dimension(0, 1, width, 12),
+        Color::BLACK,
+        Color::GRAY,
+    );
+}
+
+/// Where the end of the story is told, between the title and the keys
+fn story_rect(ctx: &mut DrawContext) -> Rect {
+    let width = ctx.get_active_layer_size().x;
+    Rect::with_dimension(2, 4, width.saturating_sub(4), 3)
+}
+
+fn print_end_screen_keys(ctx: &mut DrawContext, bindings: &KeyBindings) {
+    ctx.print_color_centered(
+        10,
+        Color::BLACK,
+        Color::GREEN,
+        &format!("({}) Play Again", bindings.key_name(Action::Restart)),
+    );
+    ctx.print_centered(
+        11,
+        &format!("({}) Quit Game", bindings.key_name(Action::Quit)),
+    );
+}
+
+fn handle_end_screen_input(
+    keyboard_input: &Input<KeyCode>,
+    bindings: &KeyBindings,
+    mut app_exit_events: EventWriter<AppExit>,
+    mut restart_events: EventWriter<RestartEvent>,
+) {
+    if bindings.just_pressed(keyboard_input, Action::Restart) {
+        restart_events.send(RestartEvent);
+    } else if bindings.just_pressed(keyboard_input, Action::Quit) {
+        app_exit_events.send(AppExit);
+    }
+}
@@ -1,19 +1,52 @@
-use crate::ascii_tilemap_plugin::DrawContext;
+use crate::ascii_tilemap_plugin::{geometry::Rect, DrawContext};
+use crate::key_bindings::{Action, Direction, KeyBindings};
 use crate::{
-    rusty_dungeon_plugin::components::{Health, Player},
-    LayerId, WIDTH,
+    rusty_dungeon_plugin::{
+        components::{Armor, Carried, Health, Item, Name, Player, Weapon},
+        DungeonDepth,
+    },
+    LayerId,
 };
 use bevy::prelude::*;
 
 #[allow(clippy::cast_sign_loss)]
-pub fn hud(mut ctx: DrawContext, query: Query<&Health, With<Player>>) {
-    let health = query.single();
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::type_complexity)]
+pub fn hud(
+    mut ctx: DrawContext,
+    query: Query<(Entity, &Health), With<Player>>,
+    carried_query: Query<(Entity, &Carried, &Name), (With<Item>, Without<Weapon>, Without<Armor>)>,
+    equipment_query: Query<(&Carried, &Name), Or<(With<Weapon>, With<Armor>)>>,
+    depth: Res<DungeonDepth>,
+    bindings: Res<KeyBindings>,
+) {
+    let (player, health) = match query.get_single() {
+        Ok(player) => player,
+        _ => return,
+    };
     ctx.set_active_layer(LayerId::Hud as u8);
-    ctx.print_centered(1, "Explore the dungeon. WASD or arrow keys to move.");
+    let move_keys = bindings.keys_hint(&[
+        Action::Move(Direction::North),
+        Action::Move(Direction::West),
+        Action::Move(Direction::South),
+        Action::Move(Direction::East),
+    ]);
+    ctx.print_rich_centered(
+        1,
+        Color::BLACK,
+        Color::WHITE,
+        &format!(
+            "Explore the dungeon. #[yellow]{}#[] to move. #[yellow]{}#[] to pick up items. #[yellow]{}#[] to save.",
+            move_keys,
+            bindings.key_name(Action::PickUp),
+            bindings.key_name(Action::Save)
+        ),
+    );
+    let width = ctx.get_active_layer_size().x;
     ctx.bar_horizontal(
         0,
         0,
-        WIDTH,
+        width,
         health.current as u32,
         health.max as u32,
         Color::BLACK,
@@ -25,4 +58,37 @@ pub fn hud(mut ctx: DrawContext, query: Query<&Health, With<Player>>) {
         Color::WHITE,
         &format!("Health: {} / {}", health.current, health.max),
     );
+    ctx.print_rich(
+        Rect::with_dimension(1, 2, width.saturating_sub(2), 1),
+        Color::BLACK,
+        Color::WHITE,
+        &format!(
+            "Dungeon Level: {}  #[yellow]{}#[] for the map",
+            depth.0,
+            bindings.key_name(Action::Minimap)
+        ),
+    );
+
+    let equipment = equipment_query
+        .iter()
+        .filter(|(carried, _)| carried.0 == player)
+        .map(|(_, name)| name.0.as_str())
+        .collect::<Vec<_>>();
+    if !equipment.is_empty() {
+        ctx.print(1, 3, &format!("Equipped: {}", equipment.join(", ")));
+    }
+
+    let mut items = carried_query
+        .iter()
+        .filter(|(_, carried, _)| carried.0 == player)
+        .map(|(entity, _, name)| (entity, name))
+        .collect::<Vec<_>>();
+    if !items.is_empty() {
+        // sorted the same way as in player_input so the numbers match
+        items.sort_by_key(|(entity, _)| *entity);
+        ctx.print_color(1, 4, Color::BLACK, Color::YELLOW, "Items carried");
+        for (i, (_, name)) in items.iter().enumerate() {
+            ctx.print(1, 5 + i as u32, &format!("{} : {}", i + 1, name.0));
+        }
+    }
 }
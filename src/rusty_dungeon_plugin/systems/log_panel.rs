@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+
+use crate::ascii_tilemap_plugin::{rich_text::wrap, DrawContext};
+use crate::rusty_dungeon_plugin::game_log::GameLog;
+use crate::LayerId;
+
+/// Height of the panel at the bottom of the screen, including its title
+const LOG_HEIGHT: u32 = 6;
+/// Number of entries scrolled by each key press
+const SCROLL_LINES: usize = 3;
+
+#[allow(clippy::cast_possible_truncation)]
+pub fn log_panel(mut ctx: DrawContext, log: Res<GameLog>) {
+    // puffin::profile_function!();
+    ctx.set_active_layer(LayerId::Hud as u8);
+    let size = ctx.get_active_layer_size();
+    let top = size.y.saturating_sub(LOG_HEIGHT);
+    let title = if log.scroll() > 0 {
+        format!("Log, {} newer (PageUp/PageDown to scroll)", log.scroll())
+    } else {
+        "Log (PageUp/PageDown to scroll)".to_string()
+    };
+    ctx.print_color(1, top, Color::BLACK, Color::YELLOW, &title);
+    // long entries are wrapped, only the newest lines fit in the panel
+    let height = LOG_HEIGHT as usize - 1;
+    let lines = log
+        .visible(height)
+        .flat_map(|entry| {
+            wrap(&entry.text, size.x.saturating_sub(2) as usize)
+                .into_iter()
+                .map(move |line| (entry.color, line))
+        })
+        .collect::<Vec<_>>();
+    let first = lines.len().saturating_sub(height);
+    for (i, (color, line)) in lines[first..].iter().enumerate() {
+        ctx.print_rich_line(1, top + 1 + i as u32, Color::BLACK, *color, line);
+    }
+}
+
+pub fn scroll_log(keyboard_input: Res<Input<KeyCode>>, mut log: ResMut<GameLog>) {
+    // puffin::profile_function!();
+    if keyboard_input.just_pressed(KeyCode::PageUp) {
+        log.scroll_up(SCROLL_LINES);
+    }
+    if keyboard_input.just_pressed(KeyCode::PageDown) {
+        log.scroll_down(SCROLL_LINES);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::ascii_tilemap_plugin::DrawContext;
-use crate::rusty_dungeon_plugin::components::Position;
+use crate::rusty_dungeon_plugin::components::{FieldOfView, Player, Position};
 use crate::{
     rusty_dungeon_plugin::{
         camera::Camera,
@@ -9,25 +9,40 @@ use crate::{
 };
 use bevy::prelude::*;
 
-pub fn map_render(mut ctx: DrawContext, map: Res<Map>, camera: Res<Camera>) {
+pub fn map_render(
+    mut ctx: DrawContext,
+    map: Res<Map>,
+    camera: Res<Camera>,
+    player_fov: Query<&FieldOfView, With<Player>>,
+) {
     // puffin::profile_function!();
+    let player_fov = player_fov.single();
     ctx.set_active_layer(LayerId::Map as u8);
     for y in camera.top_y..=camera.bottom_y {
         for x in camera.left_x..camera.right_x {
             let pos = IVec2::new(x, y);
-            if let Some(tile_type) = map.get_tile(Position(pos.as_uvec2())) {
+            if x < 0 || y < 0 {
+                continue;
+            }
+            let map_pos = Position(pos.as_uvec2());
+            let is_visible = player_fov.visible_tiles.contains(&map_pos.0);
+            if !is_visible && !map.is_revealed(map_pos) {
+                continue;
+            }
+            if let Some(tile_type) = map.get_tile(map_pos) {
                 let glyph = match tile_type {
                     TileType::Wall => '#',
                     TileType::Floor => '.',
+                    TileType::Exit => '>',
+                };
+                // remembered tiles are dimmed
+                let foreground = if is_visible {
+                    Color::WHITE
+                } else {
+                    Color::DARK_GRAY
                 };
                 let pos_offset = (pos - IVec2::new(camera.left_x, camera.top_y)).as_uvec2();
-                ctx.set(
-                    pos_offset.x,
-                    pos_offset.y,
-                    Color::BLACK,
-                    Color::WHITE,
-                    glyph,
-                );
+                ctx.set(pos_offset.x, pos_offset.y, Color::BLACK, foreground, glyph);
             }
         }
     }
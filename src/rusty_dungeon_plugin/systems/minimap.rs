@@ -0,0 +1,54 @@
+use crate::ascii_tilemap_plugin::DrawContext;
+use crate::key_bindings::{Action, KeyBindings};
+use crate::rusty_dungeon_plugin::components::{FieldOfView, Player, Position};
+use crate::{
+    rusty_dungeon_plugin::map::{Map, TileType},
+    LayerId,
+};
+use bevy::prelude::*;
+
+/// Draws the revealed tiles of the whole level with one small tile per map tile
+pub fn minimap(
+    mut ctx: DrawContext,
+    map: Res<Map>,
+    player_query: Query<(&Position, &FieldOfView), With<Player>>,
+) {
+    // puffin::profile_function!();
+    let (player, fov) = match player_query.get_single() {
+        Ok(player) => player,
+        _ => return,
+    };
+    ctx.set_active_layer(LayerId::Minimap as u8);
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let position = Position(UVec2::new(x, y));
+            let is_visible = fov.visible_tiles.contains(&position.0);
+            if !is_visible && !map.is_revealed(position) {
+                continue;
+            }
+            // the tiles are too small for glyphs, only their color is seen
+            let color = match map.get_tile(position) {
+                Some(TileType::Wall) => Color::GRAY,
+                Some(TileType::Floor) if is_visible => Color::DARK_GRAY,
+                Some(TileType::Floor) => Color::rgb(0.15, 0.15, 0.15),
+                Some(TileType::Exit) => Color::BLUE,
+                None => continue,
+            };
+            ctx.set(x, y, color, color, ' ');
+        }
+    }
+    ctx.set(player.0.x, player.0.y, Color::YELLOW, Color::BLACK, '@');
+}
+
+/// Shows or hides the minimap, it's hidden at first
+pub fn toggle_minimap(
+    mut ctx: DrawContext,
+    bindings: Res<KeyBindings>,
+    input: Res<Input<KeyCode>>,
+    mut is_shown: Local<bool>,
+) {
+    if bindings.just_pressed(&input, Action::Minimap) {
+        *is_shown = !*is_shown;
+        ctx.set_layer_visible(LayerId::Minimap as u8, *is_shown);
+    }
+}
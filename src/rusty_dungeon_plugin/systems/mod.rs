@@ -1,9 +1,16 @@
+pub mod chasing;
 pub mod combat;
 pub mod end_turn;
 pub mod entity_render;
+pub mod fov;
+pub mod game_over;
 pub mod hud;
+pub mod log_panel;
 pub mod map_render;
+pub mod minimap;
 pub mod movement;
+pub mod next_level;
 pub mod player_input;
 pub mod random_move;
 pub mod tooltips;
+pub mod use_items;
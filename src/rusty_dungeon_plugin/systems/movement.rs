@@ -1,27 +1,51 @@
 use bevy::prelude::*;
 
+use crate::key_bindings::{Action, KeyBindings};
 use crate::rusty_dungeon_plugin::{
     camera::Camera,
-    components::{Player, WantsToMove},
-    map::Map,
+    components::{FieldOfView, Player, Position, WantsToMove},
+    game_log::GameLog,
+    map::{Map, TileType},
 };
 
+#[allow(clippy::too_many_arguments)]
 pub fn movement(
     mut commands: Commands,
     mut query: Query<(Entity, &WantsToMove)>,
     player_query: Query<(), With<Player>>,
+    positions: Query<&Position>,
+    mut fov_query: Query<&mut FieldOfView>,
     map: Res<Map>,
     mut camera: ResMut<Camera>,
+    mut log: ResMut<GameLog>,
+    bindings: Res<KeyBindings>,
 ) {
     // puffin::profile_function!();
     query.for_each_mut(|(entity, wants_to_move)| {
-        if map.can_enter_tile(wants_to_move.destination) {
+        let can_enter = positions
+            .get(wants_to_move.entity)
+            .is_ok_and(|position| map.can_enter_tile_from(*position, wants_to_move.destination));
+        if can_enter {
             commands
                 .entity(wants_to_move.entity)
                 .insert(wants_to_move.destination);
+            if let Ok(mut fov) = fov_query.get_mut(wants_to_move.entity) {
+                fov.is_dirty = true;
+            }
             if player_query.get(wants_to_move.entity).is_ok() {
                 camera.on_player_move(wants_to_move.destination.0.as_ivec2());
+                if map.get_tile(wants_to_move.destination) == Some(TileType::Exit) {
+                    log.add(
+                        Color::YELLOW,
+                        format!(
+                            "You find the stairs down, press {} to descend.",
+                            bindings.key_name(Action::Descend)
+                        ),
+                    );
+                }
             }
+        } else if player_query.get(wants_to_move.entity).is_ok() {
+            log.add(Color::GRAY, "You bump into a wall.");
         }
         commands.entity(entity).despawn();
     });
@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+
+use crate::game_rng::GameRng;
+use crate::rusty_dungeon_plugin::{
+    camera::Camera,
+    components::{FieldOfView, Player, Position},
+    game_log::GameLog,
+    map::Map,
+    map_builder::ArchitectKind,
+    spawner::spawn_level,
+    templates::SpawnTemplates,
+    DungeonDepth, TurnState,
+};
+
+/// Replaces the current level with a new one, the player entity is kept as is
+#[allow(clippy::too_many_arguments)]
+pub fn next_level(
+    mut commands: Commands,
+    mut turn_state: ResMut<State<TurnState>>,
+    mut depth: ResMut<DungeonDepth>,
+    mut camera: ResMut<Camera>,
+    architect: Res<ArchitectKind>,
+    map: Res<Map>,
+    templates: Res<SpawnTemplates>,
+    mut log: ResMut<GameLog>,
+    mut rng: ResMut<GameRng>,
+    level_entities: Query<Entity, (With<Position>, Without<Player>)>,
+    mut player_query: Query<(&mut Position, &mut FieldOfView), With<Player>>,
+) {
+    // puffin::profile_function!();
+    level_entities.for_each(|entity| commands.entity(entity).despawn());
+
+    depth.0 += 1;
+    info!("Entering dungeon level {}", depth.0);
+    log.add(
+        Color::YELLOW,
+        format!("You descend to dungeon level {}.", depth.0),
+    );
+    let player_start = spawn_level(
+        &mut commands,
+        rng.rng(),
+        *architect,
+        // the rule of the game, it may come from a saved game
+        map.corner_cutting(),
+        &templates,
+        depth.0,
+    );
+
+    player_query.for_each_mut(|(mut position, mut fov)| {
+        position.0 = player_start;
+        fov.is_dirty = true;
+    });
+    camera.on_player_move(player_start.as_ivec2());
+
+    if let Err(e) = turn_state.set(TurnState::AwaitingInput) {
+        warn!("Failed to set state {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_dungeon_plugin::{
+        components::{Enemy, Health},
+        map::CornerCutting,
+        templates::SPAWNS_PATH,
+    };
+    use crate::WIDTH;
+
+    #[test]
+    fn next_level_keeps_player() {
+        let mut world = World::new();
+        world.insert_resource(State::new(TurnState::NextLevel));
+        world.insert_resource(DungeonDepth(1));
+        world.insert_resource(ArchitectKind::Rooms);
+        let mut map = Map::new(3, 3);
+        map.set_corner_cutting(CornerCutting::Allow);
+        world.insert_resource(map);
+        world.insert_resource(
+            SpawnTemplates::load(SPAWNS_PATH).expect("failed to load the spawn templates"),
+        );
+        world.insert_resource(Camera::new(IVec2::ZERO, 40, 25));
+        world.insert_resource(GameLog::default());
+        world.insert_resource(GameRng::new(42));
+        let player = world
+            .spawn()
+            .insert(Player)
+            .insert(Position(UVec2::ZERO))
+            .insert(Health {
+                current: 5,
+                max: 20,
+            })
+            .insert(FieldOfView::new(8))
+            .id();
+        let monster = world
+            .spawn()
+            .insert(Enemy)
+            .insert(Position(UVec2::ONE))
+            .id();
+
+        let mut stage = SystemStage::parallel().with_system(next_level);
+        stage.run(&mut world);
+
+        assert_eq!(world.get_resource::<DungeonDepth>().map(|d| d.0), Some(2));
+        let log = world.get_resource::<GameLog>().expect("no game log");
+        assert_eq!(log.visible(1).count(), 1);
+        assert!(world.get_entity(monster).is_none());
+        assert_eq!(world.get::<Health>(player).map(|h| h.current), Some(5));
+        let position = *world
+            .get::<Position>(player)
+            .expect("player has no position");
+        let map = world.get_resource::<Map>().expect("map not built");
+        assert!(map.can_enter_tile(position));
+        assert_eq!(map.width(), WIDTH);
+        assert_eq!(map.corner_cutting(), CornerCutting::Allow);
+    }
+}
@@ -1,25 +1,75 @@
+use crate::key_bindings::{Action, KeyBindings};
 use crate::rusty_dungeon_plugin::{
-    components::{Enemy, Health, Player, Position, WantsToAttack, WantsToMove},
+    components::{
+        ActivateItem, Armor, Carried, Enemy, Health, Item, Name, Player, Position, WantsToAttack,
+        WantsToMove, Weapon,
+    },
+    game_log::GameLog,
+    map::{Map, TileType},
+    replay::{Playback, PlayerAction},
     TurnState,
 };
 use bevy::{input::keyboard::KeyboardInput, prelude::*};
 
+/// Turns the first key pressed into an action, the keyboard is ignored during a replay
+pub fn keyboard_actions(
+    playback: Res<Playback>,
+    bindings: Res<KeyBindings>,
+    mut keyboard_input_events: EventReader<KeyboardInput>,
+    mut actions: EventWriter<PlayerAction>,
+) {
+    // puffin::profile_function!();
+    if !playback.is_finished() {
+        return;
+    }
+    // Only process the first key bound to an action, other keys don't take a turn
+    if let Some(action) = keyboard_input_events
+        .iter()
+        .filter(|x| x.state.is_pressed())
+        .filter_map(|x| x.key_code)
+        .find_map(|key_code| bindings.turn_action(key_code).and_then(player_action))
+    {
+        actions.send(action);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn player_input(
     mut commands: Commands,
     mut player_query: Query<(Entity, &Position), With<Player>>,
     enemy_query: Query<(Entity, &Position), With<Enemy>>,
+    item_query: Query<(Entity, &Position, &Name), With<Item>>,
+    carried_query: Query<(Entity, &Carried), (With<Item>, Without<Weapon>, Without<Armor>)>,
+    weapon_query: Query<(Entity, Option<&Carried>), With<Weapon>>,
+    armor_query: Query<(Entity, Option<&Carried>), With<Armor>>,
     mut player_health_query: Query<&mut Health, With<Player>>,
     mut turn_state: ResMut<State<TurnState>>,
-    mut keyboard_input_events: EventReader<KeyboardInput>,
+    mut log: ResMut<GameLog>,
+    map: Res<Map>,
+    mut actions: EventReader<PlayerAction>,
 ) {
     // puffin::profile_function!();
-    // Only process the first event
-    if let Some(event) = keyboard_input_events.iter().find(|x| x.state.is_pressed()) {
-        let delta = match event.key_code {
-            Some(KeyCode::Left | KeyCode::A) => Vec2::new(-1., 0.),
-            Some(KeyCode::Right | KeyCode::D) => Vec2::new(1., 0.),
-            Some(KeyCode::Up | KeyCode::W) => Vec2::new(0., -1.),
-            Some(KeyCode::Down | KeyCode::S) => Vec2::new(0., 1.),
+    // Only process the first action, one action per turn
+    if let Some(action) = actions.iter().next() {
+        if *action == PlayerAction::Descend {
+            let is_on_exit = player_query
+                .iter()
+                .any(|(_, position)| map.get_tile(*position) == Some(TileType::Exit));
+            if !is_on_exit {
+                // looking for stairs doesn't take a turn
+                log.add(Color::GRAY, "There are no stairs down here.");
+                return;
+            }
+            // taking the stairs doesn't give the monsters a last turn
+            if let Err(e) = turn_state.set(TurnState::NextLevel) {
+                warn!("Failed to set state {}", e);
+            }
+            return;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let delta = match *action {
+            PlayerAction::Move { dx, dy } => Vec2::new(dx as f32, dy as f32),
             _ => Vec2::ZERO,
         };
 
@@ -28,7 +78,13 @@ pub fn player_input(
             let mut did_something = false;
             if delta.x != 0. || delta.y != 0. {
                 let mut hit_something = false;
-                for (enemy, _) in enemy_query.iter().filter(|(_, pos)| **pos == destination) {
+                // enemies around a corner that can't be cut can't be attacked,
+                // moving there bumps into the wall instead
+                let can_reach = map.can_enter_tile_from(*position, destination);
+                for (enemy, _) in enemy_query
+                    .iter()
+                    .filter(|(_, pos)| can_reach && **pos == destination)
+                {
                     hit_something = true;
                     did_something = true;
                     commands.spawn().insert(WantsToAttack {
@@ -46,6 +102,52 @@ pub fn player_input(
                 }
             }
 
+            if *action == PlayerAction::PickUp {
+                // one item of a pile per turn, picked the same way in a replay
+                let item = item_query
+                    .iter()
+                    .filter(|(_, pos, _)| *pos == position)
+                    .min_by_key(|(item, _, _)| *item);
+                if let Some((item, _, name)) = item {
+                    did_something = true;
+                    // the new weapon or armor replaces the one carried
+                    if weapon_query.get(item).is_ok() {
+                        weapon_query
+                            .iter()
+                            .filter(|(_, carried)| is_carried_by(*carried, player))
+                            .for_each(|(weapon, _)| commands.entity(weapon).despawn());
+                    }
+                    if armor_query.get(item).is_ok() {
+                        armor_query
+                            .iter()
+                            .filter(|(_, carried)| is_carried_by(*carried, player))
+                            .for_each(|(armor, _)| commands.entity(armor).despawn());
+                    }
+                    commands
+                        .entity(item)
+                        .remove::<Position>()
+                        .insert(Carried(player));
+                    log.add(Color::GREEN, format!("You pick up the {}.", name.0));
+                }
+            }
+
+            if let PlayerAction::UseItem(index) = *action {
+                let mut items = carried_query
+                    .iter()
+                    .filter(|(_, carried)| carried.0 == player)
+                    .map(|(item, _)| item)
+                    .collect::<Vec<_>>();
+                // sorted the same way as in the hud so the numbers match
+                items.sort();
+                if let Some(item) = items.get(index) {
+                    did_something = true;
+                    commands.spawn().insert(ActivateItem {
+                        used_by: player,
+                        item: *item,
+                    });
+                }
+            }
+
             if !did_something {
                 match player_health_query.get_mut(player) {
                     Ok(mut health) => health.current = i32::min(health.max, health.current + 1),
@@ -59,3 +161,170 @@ pub fn player_input(
         }
     }
 }
+
+fn is_carried_by(carried: Option<&Carried>, owner: Entity) -> bool {
+    matches!(carried, Some(carried) if carried.0 == owner)
+}
+
+/// The turn taken for a bound action
+fn player_action(action: Action) -> Option<PlayerAction> {
+    match action {
+        Action::Move(direction) => {
+            let delta = direction.delta();
+            Some(PlayerAction::Move {
+                dx: delta.x,
+                dy: delta.y,
+            })
+        }
+        Action::Wait => Some(PlayerAction::Wait),
+        Action::PickUp => Some(PlayerAction::PickUp),
+        Action::UseItem(index) => Some(PlayerAction::UseItem(index)),
+        Action::Descend => Some(PlayerAction::Descend),
+        Action::Restart | Action::Quit | Action::Flap => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_dungeon_plugin::components::Damage;
+    use bevy::app::Events;
+
+    fn test_world() -> World {
+        let mut world = World::new();
+        let mut map = Map::new(5, 5);
+        map.fill(TileType::Floor);
+        world.insert_resource(map);
+        world.insert_resource(GameLog::default());
+        world.insert_resource(State::new(TurnState::AwaitingInput));
+        world.insert_resource(Events::<PlayerAction>::default());
+        world
+            .spawn()
+            .insert(Player)
+            .insert(Position(UVec2::new(1, 1)))
+            .insert(Health {
+                current: 5,
+                max: 10,
+            });
+        world
+    }
+
+    fn player(world: &mut World) -> Entity {
+        world
+            .query_filtered::<Entity, With<Player>>()
+            .iter(world)
+            .next()
+            .expect("no player")
+    }
+
+    fn spawn_weapon(world: &mut World, name: &str) -> Entity {
+        world
+            .spawn()
+            .insert(Item)
+            .insert(Weapon)
+            .insert(Damage(1))
+            .insert(Name(name.to_string()))
+            .insert(Position(UVec2::new(1, 1)))
+            .id()
+    }
+
+    /// Runs the same system again, its event reader remembers the actions already read
+    fn take_turn(stage: &mut SystemStage, world: &mut World, action: PlayerAction) {
+        world
+            .get_resource_mut::<Events<PlayerAction>>()
+            .expect("no events")
+            .send(action);
+        stage.run(world);
+    }
+
+    fn is_carried(world: &World, item: Entity) -> bool {
+        world.get::<Carried>(item).is_some() && world.get::<Position>(item).is_none()
+    }
+
+    #[test]
+    fn picks_up_an_item() {
+        let mut world = test_world();
+        let mut stage = SystemStage::parallel().with_system(player_input);
+        let potion = world
+            .spawn()
+            .insert(Item)
+            .insert(Name("Healing Potion".to_string()))
+            .insert(Position(UVec2::new(1, 1)))
+            .id();
+        let elsewhere = world
+            .spawn()
+            .insert(Item)
+            .insert(Name("Dungeon Map".to_string()))
+            .insert(Position(UVec2::new(2, 1)))
+            .id();
+
+        take_turn(&mut stage, &mut world, PlayerAction::PickUp);
+        assert!(is_carried(&world, potion));
+        assert!(!is_carried(&world, elsewhere));
+        // picking up takes the turn, the player doesn't heal
+        let player = player(&mut world);
+        assert_eq!(
+            world.get::<Health>(player).map(|health| health.current),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn uses_the_item_at_the_index() {
+        let mut world = test_world();
+        let mut stage = SystemStage::parallel().with_system(player_input);
+        let player = player(&mut world);
+        let items = (0..3)
+            .map(|_| world.spawn().insert(Item).insert(Carried(player)).id())
+            .collect::<Vec<_>>();
+        // carried by someone else, it isn't in the player's inventory
+        let other = world.spawn().id();
+        world.spawn().insert(Item).insert(Carried(other));
+
+        take_turn(&mut stage, &mut world, PlayerAction::UseItem(1));
+        let activated = world
+            .query::<&ActivateItem>()
+            .iter(&world)
+            .map(|activate| (activate.used_by, activate.item))
+            .collect::<Vec<_>>();
+        assert_eq!(activated, vec![(player, items[1])]);
+    }
+
+    #[test]
+    fn using_a_missing_item_does_nothing() {
+        let mut world = test_world();
+        let mut stage = SystemStage::parallel().with_system(player_input);
+        let player = player(&mut world);
+        world.spawn().insert(Item).insert(Carried(player));
+
+        take_turn(&mut stage, &mut world, PlayerAction::UseItem(1));
+        assert_eq!(world.query::<&ActivateItem>().iter(&world).count(), 0);
+        // nothing was done, the turn is spent resting
+        assert_eq!(
+            world.get::<Health>(player).map(|health| health.current),
+            Some(6)
+        );
+    }
+
+    #[test]
+    fn picks_up_one_weapon_per_turn() {
+        let mut world = test_world();
+        let mut stage = SystemStage::parallel().with_system(player_input);
+        let sword = spawn_weapon(&mut world, "Sword");
+        let axe = spawn_weapon(&mut world, "Axe");
+
+        take_turn(&mut stage, &mut world, PlayerAction::PickUp);
+        assert!(is_carried(&world, sword));
+        assert!(!is_carried(&world, axe));
+
+        // the axe replaces the sword
+        take_turn(&mut stage, &mut world, PlayerAction::PickUp);
+        assert!(world.get_entity(sword).is_none());
+        assert!(is_carried(&world, axe));
+        let player = player(&mut world);
+        assert_eq!(
+            world.get::<Carried>(axe).map(|carried| carried.0),
+            Some(player)
+        );
+    }
+}
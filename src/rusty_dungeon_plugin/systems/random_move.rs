@@ -1,24 +1,42 @@
 use bevy::prelude::*;
 
-use crate::rusty_dungeon_plugin::components::{
-    Health, MovingRandomly, Player, Position, WantsToAttack, WantsToMove,
+use crate::game_rng::GameRng;
+use crate::rusty_dungeon_plugin::{
+    components::{Health, MovingRandomly, Player, Position, WantsToAttack, WantsToMove},
+    map::Map,
 };
 
+/// Monsters moving randomly can step in any of the eight directions
+const DIRECTIONS: [IVec2; 8] = [
+    IVec2::new(-1, 0),
+    IVec2::new(1, 0),
+    IVec2::new(0, -1),
+    IVec2::new(0, 1),
+    IVec2::new(-1, -1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(1, 1),
+];
+
 pub fn random_move(
     mut commands: Commands,
     mut movers: Query<(Entity, &Position), With<MovingRandomly>>,
     positions: Query<(Entity, &Position, &Health)>,
     player_query: Query<&Player>,
+    map: Res<Map>,
+    mut rng: ResMut<GameRng>,
 ) {
     // puffin::profile_function!();
+    let rng = rng.rng();
     movers.for_each_mut(|(entity, pos)| {
-        let rng = fastrand::Rng::new();
-        let destination = match rng.u8(0..4) {
-            0 => IVec2::new(-1, 0),
-            1 => IVec2::new(1, 0),
-            2 => IVec2::new(0, -1),
-            _ => IVec2::new(0, 1),
-        } + pos.0.as_ivec2();
+        let destination = DIRECTIONS[rng.usize(..DIRECTIONS.len())] + pos.0.as_ivec2();
+        // can't attack around a corner it can't step around
+        if destination.x < 0
+            || destination.y < 0
+            || !map.can_enter_tile_from(*pos, Position(destination.as_uvec2()))
+        {
+            return;
+        }
         let mut attacked = false;
         positions
             .iter()
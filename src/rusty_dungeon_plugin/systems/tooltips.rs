@@ -1,9 +1,13 @@
-use crate::ascii_tilemap_plugin::DrawContext;
+use crate::ascii_tilemap_plugin::{
+    geometry::Rect,
+    rich_text::{parse, plain, TextAlign},
+    DrawContext,
+};
 use crate::rusty_dungeon_plugin::components::Position;
 use crate::{
     rusty_dungeon_plugin::{
         camera::Camera,
-        components::{Health, Name},
+        components::{FieldOfView, Health, Name, Player},
         CursorPos,
     },
     LayerId,
@@ -16,9 +20,10 @@ pub fn tooltips(
     health_query: Query<&Health>,
     cursor_pos: Res<CursorPos>,
     camera: Res<Camera>,
+    player_fov: Query<&FieldOfView, With<Player>>,
 ) {
-    let cursor_position = match cursor_pos.0 {
-        Some(cursor_pos) => cursor_pos,
+    let (cursor_position, screen_pos) = match (cursor_pos.map, cursor_pos.hud) {
+        (Some(map), Some(hud)) => (map, hud),
         _ => return,
     };
     // puffin::profile_function!();
@@ -26,16 +31,32 @@ pub fn tooltips(
 
     let offset = IVec2::new(camera.left_x, camera.top_y);
     let map_pos = cursor_position.as_ivec2() + offset;
+    if !player_fov
+        .single()
+        .visible_tiles
+        .contains(&map_pos.as_uvec2())
+    {
+        return;
+    }
     for (entity, _, name) in query
         .iter()
         .filter(|(_, pos, _)| pos.0 == map_pos.as_uvec2())
     {
-        let screen_pos = cursor_position * 2;
-        let display = if let Ok(health) = health_query.get(entity) {
-            format!("{} : {} hp", name.0, health.current)
-        } else {
-            name.0.clone()
-        };
-        ctx.print(screen_pos.x, screen_pos.y, &display);
+        // names are never parsed as markup
+        let mut display = plain(&name.0);
+        if let Ok(health) = health_query.get(entity) {
+            display.extend(parse(&format!(" : #[red]{}#[] hp", health.current)));
+        }
+        let size = ctx.get_active_layer_size();
+        if screen_pos.x >= size.x || screen_pos.y >= size.y {
+            continue;
+        }
+        let rect = Rect::with_dimension(
+            screen_pos.x,
+            screen_pos.y,
+            size.x - screen_pos.x,
+            size.y - screen_pos.y,
+        );
+        ctx.print_lines(rect, TextAlign::Left, Color::BLACK, Color::WHITE, &display);
     }
 }
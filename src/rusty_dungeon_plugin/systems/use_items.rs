@@ -0,0 +1,102 @@
+use bevy::prelude::*;
+
+use crate::rusty_dungeon_plugin::{
+    components::{ActivateItem, Health, Name, ProvidesDungeonMap, ProvidesHealing},
+    game_log::GameLog,
+    map::Map,
+};
+
+pub fn use_items(
+    mut commands: Commands,
+    activations: Query<(Entity, &ActivateItem)>,
+    healing_query: Query<&ProvidesHealing>,
+    dungeon_map_query: Query<(), With<ProvidesDungeonMap>>,
+    mut health_query: Query<&mut Health>,
+    name_query: Query<&Name>,
+    mut map: ResMut<Map>,
+    mut log: ResMut<GameLog>,
+) {
+    // puffin::profile_function!();
+    activations.for_each(|(message, activate)| {
+        if let Ok(name) = name_query.get(activate.item) {
+            log.add(Color::GREEN, format!("You use the {}.", name.0));
+        }
+        if let Ok(healing) = healing_query.get(activate.item) {
+            if let Ok(mut health) = health_query.get_mut(activate.used_by) {
+                health.current = i32::min(health.max, health.current + healing.amount);
+            }
+        }
+        if dungeon_map_query.get(activate.item).is_ok() {
+            map.reveal_all();
+        }
+        commands.entity(activate.item).despawn();
+        commands.entity(message).despawn();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rusty_dungeon_plugin::components::Position;
+
+    fn test_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(Map::new(5, 5));
+        world.insert_resource(GameLog::default());
+        world
+    }
+
+    /// The player uses an item, returns the player
+    fn use_item(world: &mut World, item: Entity) -> Entity {
+        let player = world
+            .spawn()
+            .insert(Health {
+                current: 2,
+                max: 10,
+            })
+            .id();
+        world.spawn().insert(ActivateItem {
+            used_by: player,
+            item,
+        });
+        let mut stage = SystemStage::parallel().with_system(use_items);
+        stage.run(world);
+        player
+    }
+
+    #[test]
+    fn healing_is_capped_at_max_health() {
+        let mut world = test_world();
+        let potion = world
+            .spawn()
+            .insert(Name("Healing Potion".to_string()))
+            .insert(ProvidesHealing { amount: 6 })
+            .id();
+        let player = use_item(&mut world, potion);
+        assert_eq!(
+            world.get::<Health>(player).map(|health| health.current),
+            Some(8)
+        );
+        // the item and the message are used up
+        assert!(world.get_entity(potion).is_none());
+        assert_eq!(world.query::<&ActivateItem>().iter(&world).count(), 0);
+
+        let potion = world.spawn().insert(ProvidesHealing { amount: 20 }).id();
+        let player = use_item(&mut world, potion);
+        assert_eq!(
+            world.get::<Health>(player).map(|health| health.current),
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn dungeon_map_reveals_the_level() {
+        let mut world = test_world();
+        let dungeon_map = world.spawn().insert(ProvidesDungeonMap).id();
+        use_item(&mut world, dungeon_map);
+        let map = world.get_resource::<Map>().expect("no map");
+        assert!(map.is_revealed(Position(UVec2::new(0, 0))));
+        assert!(map.is_revealed(Position(UVec2::new(4, 4))));
+        assert!(world.get_entity(dungeon_map).is_none());
+    }
+}
@@ -0,0 +1,279 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+use bevy::prelude::*;
+use fastrand::Rng;
+use serde::Deserialize;
+
+/// Where the spawn templates are loaded from, relative to the working directory
+pub const SPAWNS_PATH: &str = "assets/spawns.ron";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum EntityType {
+    Enemy,
+    Item,
+    Weapon,
+    Armor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Ai {
+    MovingRandomly,
+    ChasingPlayer,
+}
+
+/// What happens when an item is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Effect {
+    Healing(i32),
+    DungeonMap,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Template {
+    pub entity_type: EntityType,
+    pub name: String,
+    pub glyph: char,
+    /// Foreground color as a hex string
+    pub color: String,
+    pub frequency: u32,
+    pub min_depth: u32,
+    pub max_depth: u32,
+    #[serde(default)]
+    pub hp: Option<i32>,
+    /// Base damage of an enemy or damage added by a weapon
+    #[serde(default)]
+    pub damage: Option<i32>,
+    #[serde(default)]
+    pub defense: Option<i32>,
+    #[serde(default)]
+    pub ai: Option<Ai>,
+    #[serde(default)]
+    pub effect: Option<Effect>,
+}
+
+impl Template {
+    pub fn color(&self) -> Color {
+        // the color is checked when the templates are loaded
+        Color::hex(&self.color).unwrap_or(Color::WHITE)
+    }
+
+    fn is_available_at(&self, depth: u32) -> bool {
+        (self.min_depth..=self.max_depth).contains(&depth)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            bail!("name is empty");
+        }
+        if self.frequency == 0 {
+            bail!("frequency must be greater than 0");
+        }
+        if self.min_depth == 0 || self.min_depth > self.max_depth {
+            bail!(
+                "invalid depth range {}..={}, depths start at 1",
+                self.min_depth,
+                self.max_depth
+            );
+        }
+        Color::hex(&self.color).map_err(|e| anyhow!("invalid color {:?}: {:?}", self.color, e))?;
+        if let Some(damage) = self.damage {
+            if damage < 0 {
+                bail!("damage must not be negative");
+            }
+        }
+        match self.entity_type {
+            EntityType::Enemy => match self.hp {
+                Some(hp) if hp > 0 => {}
+                Some(hp) => bail!("hp must be greater than 0, got {}", hp),
+                None => bail!("enemies need hp"),
+            },
+            EntityType::Item => {
+                if self.effect.is_none() {
+                    bail!("items need an effect");
+                }
+            }
+            EntityType::Weapon => {
+                if self.damage.is_none() {
+                    bail!("weapons need damage");
+                }
+            }
+            EntityType::Armor => match self.defense {
+                Some(defense) if defense >= 0 => {}
+                Some(defense) => bail!("defense must not be negative, got {}", defense),
+                None => bail!("armors need defense"),
+            },
+        }
+        Ok(())
+    }
+}
+
+/// The monsters and items that can be spawned in the dungeon
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpawnTemplates {
+    pub entities: Vec<Template>,
+}
+
+impl SpawnTemplates {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read spawn templates from {}", path.display()))?;
+        Self::from_ron(&text)
+            .with_context(|| format!("failed to load spawn templates from {}", path.display()))
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self> {
+        let templates: Self = ron::from_str(text).context("failed to parse spawn templates")?;
+        if templates.entities.is_empty() {
+            bail!("no spawn templates");
+        }
+        for (i, template) in templates.entities.iter().enumerate() {
+            template
+                .validate()
+                .with_context(|| format!("invalid template #{} {:?}", i, template.name))?;
+        }
+        Ok(templates)
+    }
+
+    /// Picks a template available at the given depth, weighted by frequency
+    pub fn choose(&self, rng: &mut Rng, depth: u32) -> Option<&Template> {
+        let total = self
+            .entities
+            .iter()
+            .filter(|template| template.is_available_at(depth))
+            .map(|template| template.frequency)
+            .sum::<u32>();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = rng.u32(0..total);
+        self.entities
+            .iter()
+            .filter(|template| template.is_available_at(depth))
+            .find(|template| {
+                if roll < template.frequency {
+                    true
+                } else {
+                    roll -= template.frequency;
+                    false
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATES: &str = r#"(
+        entities: [
+            (
+                entity_type: Enemy,
+                name: "Goblin",
+                glyph: 'g',
+                color: "ffffff",
+                frequency: 3,
+                min_depth: 1,
+                max_depth: 2,
+                hp: Some(1),
+                damage: Some(1),
+                ai: Some(MovingRandomly),
+            ),
+            (
+                entity_type: Item,
+                name: "Healing Potion",
+                glyph: '!',
+                color: "ff0000",
+                frequency: 1,
+                min_depth: 2,
+                max_depth: 3,
+                effect: Some(Healing(6)),
+            ),
+            (
+                entity_type: Armor,
+                name: "Leather Armor",
+                glyph: '[',
+                color: "ffffff",
+                frequency: 1,
+                min_depth: 3,
+                max_depth: 3,
+                defense: Some(1),
+            ),
+        ],
+    )"#;
+
+    #[test]
+    fn parse_templates() {
+        let templates = SpawnTemplates::from_ron(TEMPLATES).expect("failed to parse");
+        assert_eq!(templates.entities.len(), 3);
+        let goblin = &templates.entities[0];
+        assert_eq!(goblin.entity_type, EntityType::Enemy);
+        assert_eq!(goblin.glyph, 'g');
+        assert_eq!(goblin.hp, Some(1));
+        assert_eq!(goblin.ai, Some(Ai::MovingRandomly));
+        let potion = &templates.entities[1];
+        assert_eq!(potion.hp, None);
+        assert_eq!(potion.effect, Some(Effect::Healing(6)));
+        let armor = &templates.entities[2];
+        assert_eq!(armor.entity_type, EntityType::Armor);
+        assert_eq!(armor.defense, Some(1));
+    }
+
+    #[test]
+    fn parse_assets() {
+        SpawnTemplates::load(SPAWNS_PATH).expect("failed to load the spawn templates");
+    }
+
+    #[test]
+    fn invalid_templates() {
+        let invalid = [
+            TEMPLATES.replace("frequency: 3", "frequency: 0"),
+            TEMPLATES.replace("min_depth: 1", "min_depth: 3"),
+            TEMPLATES.replace("\"ffffff\"", "\"white\""),
+            TEMPLATES.replace("hp: Some(1),", ""),
+            TEMPLATES.replace("effect: Some(Healing(6)),", ""),
+            TEMPLATES.replace("defense: Some(1)", "defense: Some(-1)"),
+            TEMPLATES.replace("glyph: 'g'", "glyph: \"g\""),
+            "(entities: [])".to_string(),
+        ];
+        for text in invalid {
+            assert!(SpawnTemplates::from_ron(&text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn invalid_template_error_names_template() {
+        let text = TEMPLATES.replace("frequency: 3", "frequency: 0");
+        let error = SpawnTemplates::from_ron(&text).expect_err("should fail");
+        assert!(format!("{:#}", error).contains("Goblin"));
+    }
+
+    #[test]
+    fn choose_respects_depth() {
+        let templates = SpawnTemplates::from_ron(TEMPLATES).expect("failed to parse");
+        let mut rng = Rng::new();
+        rng.seed(42);
+        for _ in 0..100 {
+            let depth_1 = templates.choose(&mut rng, 1).map(|t| t.name.as_str());
+            assert_eq!(depth_1, Some("Goblin"));
+            let depth_3 = templates.choose(&mut rng, 3).map(|t| t.name.as_str());
+            assert!(matches!(depth_3, Some("Healing Potion" | "Leather Armor")));
+        }
+        assert!(templates.choose(&mut rng, 4).is_none());
+    }
+
+    #[test]
+    fn choose_is_weighted_by_frequency() {
+        let templates = SpawnTemplates::from_ron(TEMPLATES).expect("failed to parse");
+        let mut rng = Rng::new();
+        rng.seed(42);
+        let goblins = (0..4000)
+            .filter_map(|_| templates.choose(&mut rng, 2))
+            .filter(|t| t.name == "Goblin")
+            .count();
+        // 3 goblins for 1 potion
+        assert!((2800..3200).contains(&goblins), "{}", goblins);
+    }
+}